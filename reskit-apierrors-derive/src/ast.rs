@@ -1,16 +1,11 @@
 use crate::attr::{self, Attrs};
-use proc_macro2::Span;
-use syn::{
-    Data, DataEnum, DeriveInput, Error, Fields, Generics, Ident, Index, Member, Result,
-    Type,
-};
+use syn::{Data, DataEnum, DeriveInput, Error, Fields, Generics, Ident, Result};
 
 pub enum Input<'a> {
     Enum(Enum<'a>),
 }
 
 pub struct Enum<'a> {
-    pub original: &'a DeriveInput,
     pub attrs: Attrs<'a>,
     pub ident: Ident,
     pub generics: &'a Generics,
@@ -25,10 +20,7 @@ pub struct Variant<'a> {
 }
 
 pub struct Field<'a> {
-    pub original: &'a syn::Field,
     pub attrs: Attrs<'a>,
-    pub member: Member,
-    pub ty: &'a Type,
 }
 
 impl<'a> Input<'a> {
@@ -50,12 +42,11 @@ impl<'a> Input<'a> {
 impl<'a> Enum<'a> {
     fn from_syn(node: &'a DeriveInput, data: &'a DataEnum) -> Result<Self> {
         let attrs = attr::get(&node.attrs)?;
-        let span = attrs.span().unwrap_or_else(Span::call_site);
         let variants = data
             .variants
             .iter()
             .map(|node| {
-                let mut variant = Variant::from_syn(node, span)?;
+                let mut variant = Variant::from_syn(node)?;
                 if let meta @ None = &mut variant.attrs.meta {
                     *meta = attrs.meta.clone();
                 }
@@ -63,7 +54,6 @@ impl<'a> Enum<'a> {
             })
             .collect::<Result<_>>()?;
         Ok(Enum {
-            original: node,
             attrs,
             ident: node.ident.clone(),
             generics: &node.generics,
@@ -73,48 +63,24 @@ impl<'a> Enum<'a> {
 }
 
 impl<'a> Variant<'a> {
-    fn from_syn(node: &'a syn::Variant, span: Span) -> Result<Self> {
-        let attrs = attr::get(&node.attrs)?;
-        let span = attrs.span().unwrap_or(span);
+    fn from_syn(node: &'a syn::Variant) -> Result<Self> {
         Ok(Variant {
             original: node,
-            attrs,
+            attrs: attr::get(&node.attrs)?,
             ident: node.ident.clone(),
-            fields: Field::multiple_from_syn(&node.fields, span)?,
+            fields: Field::multiple_from_syn(&node.fields)?,
         })
     }
 }
 
 impl<'a> Field<'a> {
-    fn multiple_from_syn(fields: &'a Fields, span: Span) -> Result<Vec<Self>> {
-        fields
-            .iter()
-            .enumerate()
-            .map(|(i, field)| Field::from_syn(i, field, span))
-            .collect()
+    fn multiple_from_syn(fields: &'a Fields) -> Result<Vec<Self>> {
+        fields.iter().map(Field::from_syn).collect()
     }
 
-    fn from_syn(i: usize, node: &'a syn::Field, span: Span) -> Result<Self> {
+    fn from_syn(node: &'a syn::Field) -> Result<Self> {
         Ok(Field {
-            original: node,
             attrs: attr::get(&node.attrs)?,
-            member: node.ident.clone().map(Member::Named).unwrap_or_else(|| {
-                Member::Unnamed(Index {
-                    index: i as u32,
-                    span,
-                })
-            }),
-            ty: &node.ty,
         })
     }
-}
-
-impl Attrs<'_> {
-    pub fn span(&self) -> Option<Span> {
-        if let Some(meta) = &self.meta {
-            Some(meta.system.span())
-        } else {
-            None
-        }
-    }
 }
\ No newline at end of file
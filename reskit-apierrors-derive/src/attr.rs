@@ -1,13 +1,23 @@
-use proc_macro2::{TokenStream, Span};
+use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 use syn::parse::ParseStream;
 use syn::{
-    Attribute, Ident, Error, LitInt, LitStr,
-    Result, Token,
+    Attribute, Ident, Error, Lit, LitInt, LitStr,
+    Path, Result, Token,
 };
 
 pub struct Attrs<'a> {
     pub meta: Option<Meta<'a>>,
+    pub doc: Option<String>,
+    pub metas: Option<Metas>,
+}
+
+/// Metas are the enum level defaults, `#[apierrormetas(pvlost="local_error")]` or
+/// `#[apierrormetas(pvlost_policy="path::to::policy")]`
+#[derive(Clone, Default)]
+pub struct Metas {
+    pub pvlost: Option<PVLost>,
+    pub pvlost_policy: Option<Path>,
 }
 
 #[derive(Clone)]
@@ -17,28 +27,85 @@ pub struct Meta<'a> {
     pub code: LitStr,
     pub message: LitStr,
     pub status_code: LitInt,
-    pub pvlost: LitInt,
+    pub pvlost: Option<PVLost>,
+    pub description: Option<LitStr>,
+    pub retryable: Option<Ident>,
+    pub retry_after: Option<LitInt>,
+    pub severity: Option<Ident>,
+}
+
+/// PVLost is the pvlost attribute value, a code, e.g. `pvlost=2`, builtin reason names, e.g.
+/// `pvlost="local_error"`, are resolved to their code
+#[derive(Clone)]
+pub struct PVLost(LitInt);
+
+impl ToTokens for PVLost {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let lit = &self.0;
+        let expanded = match lit.base10_parse::<u8>() {
+            Ok(0) => quote!(crate::PVLost::Successful),
+            Ok(1) => quote!(crate::PVLost::RemoteError),
+            Ok(2) => quote!(crate::PVLost::LocalError),
+            // reasons registered at runtime keep the declared code, unregistered codes count as lost
+            _ => quote!(crate::PVLost::new(#lit)),
+        };
+        expanded.to_tokens(tokens);
+    }
 }
 
-pub fn get(input: &[Attribute]) -> Result<Attrs> {
+pub fn get(input: &[Attribute]) -> Result<Attrs<'_>> {
     let mut attrs = Attrs {
         meta: None,
+        doc: None,
+        metas: None,
     };
+    let mut doc_lines = Vec::new();
     for attr in input {
         if attr.path.is_ident("apierrormeta") {
             parse_apierrormeta_attribute(&mut attrs, attr)?;
+        } else if attr.path.is_ident("apierrormetas") {
+            parse_apierrormetas_attribute(&mut attrs, attr)?;
+        } else if attr.path.is_ident("doc") {
+            if let syn::Meta::NameValue(meta) = attr.parse_meta()? {
+                if let Lit::Str(lit) = meta.lit {
+                    doc_lines.push(lit.value());
+                }
+            }
         }
     }
+    attrs.doc = unindent_doc(&doc_lines);
     Ok(attrs)
 }
 
+/// unindent_doc join `///` lines or a `/** */` block, strip the common indentation and the blank lines around
+fn unindent_doc(doc_lines: &[String]) -> Option<String> {
+    let joined = doc_lines.join("\n");
+    let lines: Vec<&str> = joined.lines().map(str::trim_end).collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()?;
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| if line.is_empty() { line } else { &line[indent..] })
+        .skip_while(|line| line.is_empty())
+        .collect();
+    let end = lines.iter().rposition(|line| !line.is_empty())?;
+    Some(lines[..=end].join("\n"))
+}
+
 fn parse_apierrormeta_attribute<'a>(attrs: &mut Attrs<'a>, attr: &'a Attribute) -> Result<()> {
     attr.parse_args_with(|input: ParseStream| {
         let mut lit_system: Option<LitStr> = None;
         let mut lit_code: Option<LitStr> = None;
         let mut lit_message: Option<LitStr> = None;
         let mut lit_status_code: Option<LitInt> = None;
-        let mut lit_pvlost: Option<LitInt> = None;
+        let mut pvlost: Option<PVLost> = None;
+        let mut lit_description: Option<LitStr> = None;
+        let mut retryable: Option<Ident> = None;
+        let mut lit_retry_after: Option<LitInt> = None;
+        let mut severity: Option<Ident> = None;
 
         let lookahead = input.lookahead1();
         while lookahead.peek(Ident) {
@@ -63,7 +130,12 @@ fn parse_apierrormeta_attribute<'a>(attrs: &mut Attrs<'a>, attr: &'a Attribute)
                         ));
                     }
                     let _: Token![=] = input.parse()?;
-                    let lit = input.parse::<LitStr>()?;
+                    // numeric codes, e.g. `code=27`, are the same as their string, `code="27"`
+                    let lit = match input.parse::<Lit>()? {
+                        Lit::Str(lit) => lit,
+                        Lit::Int(lit) => LitStr::new(&lit.base10_parse::<u32>()?.to_string(), lit.span()),
+                        lit => return Err(Error::new_spanned(lit, "code should be a string or an integer")),
+                    };
                     lit_code = Some(lit);
                 },
                 "message" => {
@@ -86,18 +158,65 @@ fn parse_apierrormeta_attribute<'a>(attrs: &mut Attrs<'a>, attr: &'a Attribute)
                     }
                     let _: Token![=] = input.parse()?;
                     let lit  = input.parse::<LitInt>()?;
+                    if !(100..=599).contains(&lit.base10_parse::<u16>()?) {
+                        return Err(Error::new_spanned(lit, "status_code should be in 100..=599"));
+                    }
                     lit_status_code = Some(lit);
                 },
                 "pvlost" => {
-                    if lit_pvlost.is_some() {
+                    if pvlost.is_some() {
                         return Err(Error::new_spanned(
                             attr,
                             "duplicate #[apierrormeta(pvlost)] attribute",
                         ));
                     }
                     let _: Token![=] = input.parse()?;
+                    pvlost = Some(parse_pvlost(input)?);
+                },
+                "description" => {
+                    if lit_description.is_some() {
+                        return Err(Error::new_spanned(
+                            attr,
+                            "duplicate #[apierrormeta(description)] attribute",
+                        ));
+                    }
+                    let _: Token![=] = input.parse()?;
+                    let lit = input.parse::<LitStr>()?;
+                    lit_description = Some(lit);
+                },
+                "retryable" => {
+                    if retryable.is_some() {
+                        return Err(Error::new_spanned(
+                            attr,
+                            "duplicate #[apierrormeta(retryable)] attribute",
+                        ));
+                    }
+                    let _: Token![=] = input.parse()?;
+                    let lit = input.parse::<LitStr>()?;
+                    retryable = Some(retryable_variant(&lit)?);
+                },
+                "retry_after" => {
+                    if lit_retry_after.is_some() {
+                        return Err(Error::new_spanned(
+                            attr,
+                            "duplicate #[apierrormeta(retry_after)] attribute",
+                        ));
+                    }
+                    let _: Token![=] = input.parse()?;
                     let lit = input.parse::<LitInt>()?;
-                    lit_pvlost = Some(lit);
+                    lit.base10_parse::<u64>()?;
+                    lit_retry_after = Some(lit);
+                },
+                "severity" => {
+                    if severity.is_some() {
+                        return Err(Error::new_spanned(
+                            attr,
+                            "duplicate #[apierrormeta(severity)] attribute",
+                        ));
+                    }
+                    let _: Token![=] = input.parse()?;
+                    let lit = input.parse::<LitStr>()?;
+                    severity = Some(severity_variant(&lit)?);
                 },
                 _ => {
                     return Err(Error::new_spanned(
@@ -118,10 +237,11 @@ fn parse_apierrormeta_attribute<'a>(attrs: &mut Attrs<'a>, attr: &'a Attribute)
             code: lit_code.expect("code should not be None"),
             message: lit_message.expect("message should not be None"),
             status_code: lit_status_code.expect("status_code should not be None"),
-            pvlost: match lit_pvlost {
-                Some(lit) => lit,
-                None => LitInt::new("1", Span::call_site()), // NOTE: set default!
-            },
+            pvlost,
+            description: lit_description,
+            retryable,
+            retry_after: lit_retry_after,
+            severity,
         }; 
         if attrs.meta.is_some() {
             return Err(Error::new_spanned(attr,"only one #[apierrormeta(...)] attribute is allowed"));
@@ -131,6 +251,86 @@ fn parse_apierrormeta_attribute<'a>(attrs: &mut Attrs<'a>, attr: &'a Attribute)
     })
 }
 
+fn parse_apierrormetas_attribute(attrs: &mut Attrs, attr: &Attribute) -> Result<()> {
+    if attrs.metas.is_some() {
+        return Err(Error::new_spanned(attr, "only one #[apierrormetas(...)] attribute is allowed"));
+    }
+    attr.parse_args_with(|input: ParseStream| {
+        let mut metas = Metas::default();
+        while !input.is_empty() {
+            let ident = input.parse::<Ident>()?;
+            let _: Token![=] = input.parse()?;
+            match ident.to_string().as_str() {
+                "pvlost" if metas.pvlost.is_none() => metas.pvlost = Some(parse_pvlost(input)?),
+                "pvlost_policy" if metas.pvlost_policy.is_none() => {
+                    let lit = input.parse::<LitStr>()?;
+                    metas.pvlost_policy = Some(lit.parse()?);
+                },
+                "pvlost" | "pvlost_policy" => {
+                    return Err(Error::new_spanned(ident, "duplicate #[apierrormetas] attribute field"));
+                },
+                _ => return Err(Error::new_spanned(ident, "unknown apierrormetas field")),
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        if metas.pvlost.is_some() && metas.pvlost_policy.is_some() {
+            return Err(Error::new_spanned(attr, "pvlost and pvlost_policy are exclusive"));
+        }
+        attrs.metas = Some(metas);
+        Ok(())
+    })
+}
+
+/// parse_pvlost parses a pvlost code, e.g. `2`, or a builtin reason name, e.g. `"local_error"`
+fn parse_pvlost(input: ParseStream) -> Result<PVLost> {
+    match input.parse::<Lit>()? {
+        Lit::Int(lit) => {
+            lit.base10_parse::<u8>()?;
+            Ok(PVLost(lit))
+        },
+        Lit::Str(lit) => {
+            let code = match lit.value().as_str() {
+                "successful" => "0",
+                "remote_error" => "1",
+                "local_error" => "2",
+                _ => return Err(Error::new_spanned(
+                    &lit,
+                    "pvlost should be one of \"successful\", \"remote_error\" or \"local_error\", use the code of reasons registered at runtime",
+                )),
+            };
+            Ok(PVLost(LitInt::new(code, lit.span())))
+        },
+        lit => Err(Error::new_spanned(lit, "pvlost should be a code or a reason name")),
+    }
+}
+
+/// retryable_variant maps the retryable attribute value to the variant of `Retryable`
+fn retryable_variant(lit: &LitStr) -> Result<Ident> {
+    let variant = match lit.value().as_str() {
+        "never" => "Never",
+        "safe" => "Safe",
+        "idempotent_only" => "IdempotentOnly",
+        _ => return Err(Error::new_spanned(lit, "retryable should be one of \"never\", \"safe\" or \"idempotent_only\"")),
+    };
+    Ok(Ident::new(variant, lit.span()))
+}
+
+/// severity_variant maps the severity attribute value to the variant of `Severity`
+fn severity_variant(lit: &LitStr) -> Result<Ident> {
+    let variant = match lit.value().as_str() {
+        "debug" => "Debug",
+        "info" => "Info",
+        "warn" => "Warn",
+        "error" => "Error",
+        "critical" => "Critical",
+        _ => return Err(Error::new_spanned(lit, "severity should be one of \"debug\", \"info\", \"warn\", \"error\" or \"critical\"")),
+    };
+    Ok(Ident::new(variant, lit.span()))
+}
+
 impl ToTokens for Meta<'_> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         tokens.extend(quote! { &self });
@@ -88,7 +88,7 @@ fn impl_enum(input: Enum) -> TokenStream {
                     let ident = &variant.ident;
                     let status_code = &meta.status_code;
                     Some(quote! {
-                        #ty::#ident => http_types::StatusCode::try_from(#status_code).unwrap(),
+                        #ty::#ident => crate::Status::new(#status_code),
                     })
                 }
                 None => None,
@@ -96,6 +96,10 @@ fn impl_enum(input: Enum) -> TokenStream {
         });
         Some(quote! {
             fn status_code(&self) -> http_types::StatusCode {
+                self.status().to_http()
+            }
+
+            fn status(&self) -> crate::Status {
                 match self {
                     #(#arms)*
                 }
@@ -103,21 +107,131 @@ fn impl_enum(input: Enum) -> TokenStream {
         })
     };
 
-    #[cfg(not(feature = "pvlost"))]
-    let pvlost_method: Option<TokenStream> = None;
+    let description_method = {
+        let arms = input.variants.iter().map(|variant| {
+            match &variant.attrs.meta {
+                Some(meta) => {
+                    let ident = &variant.ident;
+                    let description = match &meta.description {
+                        Some(description) => description.value(),
+                        None => variant.attrs.doc.clone().unwrap_or_default(),
+                    };
+                    Some(quote! {
+                        #ty::#ident => #description,
+                    })
+                }
+                None => None,
+            }
+        });
+        Some(quote! {
+            fn description(&self) -> &str {
+                match self {
+                    #(#arms)*
+                }
+            }
+        })
+    };
 
-    #[cfg(feature = "pvlost")]
-    let pvlost_method = {
+    // NOTE: retry methods are only generated if some variant sets them, the trait defaults apply otherwise
+    let retryable_method = if input.variants.iter().any(|variant| variant.attrs.meta.as_ref().is_some_and(|meta| meta.retryable.is_some())) {
+        let arms = input.variants.iter().map(|variant| {
+            match &variant.attrs.meta {
+                Some(meta) => {
+                    let ident = &variant.ident;
+                    let retryable = match &meta.retryable {
+                        Some(retryable) => quote! { crate::Retryable::#retryable },
+                        None => quote! { crate::Retryable::Never },
+                    };
+                    Some(quote! {
+                        #ty::#ident => #retryable,
+                    })
+                }
+                None => None,
+            }
+        });
+        Some(quote! {
+            fn retryable(&self) -> crate::Retryable {
+                match self {
+                    #(#arms)*
+                }
+            }
+        })
+    } else {
+        None
+    };
+
+    let retry_after_method = if input.variants.iter().any(|variant| variant.attrs.meta.as_ref().is_some_and(|meta| meta.retry_after.is_some())) {
+        let arms = input.variants.iter().map(|variant| {
+            match &variant.attrs.meta {
+                Some(meta) => {
+                    let ident = &variant.ident;
+                    let retry_after = match &meta.retry_after {
+                        Some(secs) => quote! { Some(std::time::Duration::from_secs(#secs)) },
+                        None => quote! { None },
+                    };
+                    Some(quote! {
+                        #ty::#ident => #retry_after,
+                    })
+                }
+                None => None,
+            }
+        });
+        Some(quote! {
+            fn retry_after(&self) -> Option<std::time::Duration> {
+                match self {
+                    #(#arms)*
+                }
+            }
+        })
+    } else {
+        None
+    };
+
+    let severity_method = if input.variants.iter().any(|variant| variant.attrs.meta.as_ref().is_some_and(|meta| meta.severity.is_some())) {
         let arms = input.variants.iter().map(|variant| {
             match &variant.attrs.meta {
                 Some(meta) => {
                     let ident = &variant.ident;
-                    let pvlost = &meta.pvlost;
+                    let severity = match &meta.severity {
+                        Some(severity) => quote! { crate::Severity::#severity },
+                        None => quote! { crate::Severity::infer(self) },
+                    };
                     Some(quote! {
-                        #ty::#ident => crate::PVLost::try_from(#pvlost).unwrap(),
+                        #ty::#ident => #severity,
                     })
                 }
-                _ => None,
+                None => None,
+            }
+        });
+        Some(quote! {
+            fn severity(&self) -> crate::Severity {
+                match self {
+                    #(#arms)*
+                }
+            }
+        })
+    } else {
+        None
+    };
+
+    let metas = input.attrs.metas.clone().unwrap_or_default();
+    let pvlost_method = if metas.pvlost.is_some()
+        || metas.pvlost_policy.is_some()
+        || input.variants.iter().any(|variant| variant.attrs.meta.as_ref().is_some_and(|meta| meta.pvlost.is_some())) {
+        let arms = input.variants.iter().map(|variant| {
+            match &variant.attrs.meta {
+                Some(meta) => {
+                    let ident = &variant.ident;
+                    let pvlost = match (&meta.pvlost, &metas.pvlost, &metas.pvlost_policy) {
+                        (Some(pvlost), _, _) | (None, Some(pvlost), _) => quote! { #pvlost },
+                        (None, None, Some(policy)) => quote! { #policy(self.status_code()) },
+                        (None, None, None) => quote! { crate::PVLost::infer(self.status_code()) },
+                    };
+                    Some(quote! {
+                        #ty::#ident => #pvlost,
+                    })
+                }
+                None => None,
             }
         });
         Some(quote! {
@@ -127,6 +241,8 @@ fn impl_enum(input: Enum) -> TokenStream {
                 }
             }
         })
+    } else {
+        None
     };
 
     let apierrormetas_impl = {
@@ -154,7 +270,7 @@ fn impl_enum(input: Enum) -> TokenStream {
             #[allow(unused_qualifications)]
             impl #impl_generics std::fmt::Display for #ty #ty_generics #where_clause {
                 fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                    write!(f, "{}:{}:{}:{}", self.status_code(), self.system(), self.code(), self.message())  
+                    write!(f, "{}:{}:{}:{}", self.status(), self.system(), self.code(), self.message())  
                 }
             }
         })
@@ -166,14 +282,13 @@ fn impl_enum(input: Enum) -> TokenStream {
             #[allow(unused_qualifications)]
             impl #impl_generics std::fmt::Display for #ty #ty_generics #where_clause {
                 fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                    write!(f, "{}:{}:{}:{}:{}", self.status_code(), self.system(), self.code(), self.message(), self.pvlost() as u8)
+                    write!(f, "{}:{}:{}:{}:{}", self.status(), self.system(), self.code(), self.message(), u8::from(self.pvlost()))
                 }
             }
         })
     };
 
     quote! {
-        use std::convert::TryFrom;
         #[allow(unused_qualifications)]
         impl #impl_generics crate::APIErrorMeta for #ty #ty_generics #where_clause {
             #system_method
@@ -181,6 +296,10 @@ fn impl_enum(input: Enum) -> TokenStream {
             #message_method
             #status_code_method
             #pvlost_method
+            #description_method
+            #retryable_method
+            #retry_after_method
+            #severity_method
         }
         #apierrormetas_impl
         #display_impl
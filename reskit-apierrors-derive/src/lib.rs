@@ -1,5 +1,5 @@
 #![allow(
-    clippy::blocks_in_if_conditions,
+    clippy::blocks_in_conditions,
     clippy::cast_possible_truncation,
     clippy::manual_map,
     clippy::map_unwrap_or,
@@ -20,11 +20,10 @@ mod valid;
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
-#[proc_macro_derive(AsAPIErrorMeta, attributes(apierrormeta))]
+#[proc_macro_derive(AsAPIErrorMeta, attributes(apierrormeta, apierrormetas))]
 pub fn derive_apierrormeta(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let tokens = expand::derive(&input)
+    expand::derive(&input)
         .unwrap_or_else(|err| err.to_compile_error())
-        .into();
-    tokens
+        .into()
 }
\ No newline at end of file
@@ -28,12 +28,21 @@ impl Variant<'_> {
                 "missing #[error(transparent)] attribute",
             ));
         }
+        if self.attrs.metas.is_some() {
+            return Err(Error::new_spanned(
+                self.original,
+                "not expected here; the #[apierrormetas] attribute belongs on the enum",
+            ));
+        }
+        for field in &self.fields {
+            field.validate()?;
+        }
         Ok(())
     }
 }
 
 impl Field<'_> {
-    fn _validate(&self) -> Result<()> {
+    fn validate(&self) -> Result<()> {
         if let Some(meta) = &self.attrs.meta {
             return Err(Error::new_spanned(
                 meta.original,
@@ -1,10 +1,17 @@
+use std::borrow::Cow;
 use std::fmt::{Display, Result, Formatter, Debug};
 use std::error::Error;
+use std::time::Duration;
 
 use http_types::StatusCode;
 
-#[cfg(feature = "pvlost")]
-use crate::PVLost;
+use crate::globals::{global_code_errorspace, redaction_errorspace, resolve_message_errorspace, ErrorspaceName};
+use crate::locale::parse_accept_language;
+use crate::scope::current_errorspace;
+use crate::retry::Retryable;
+use crate::severity::Severity;
+use crate::pvlost::PVLost;
+use crate::status_code::Status;
 
 pub trait APIErrorMeta: Sync + Send + Debug + Display {
     fn system(&self) -> &str;
@@ -12,8 +19,43 @@ pub trait APIErrorMeta: Sync + Send + Debug + Display {
     fn message(&self) -> &str;
     fn status_code(&self) -> StatusCode;
 
-    #[cfg(feature = "pvlost")]
-    fn pvlost(&self) -> PVLost;
+    /// numeric_code is the code as an integer, None if it is not numeric, e.g. `code="27"` or `code=27`
+    fn numeric_code(&self) -> Option<u32> {
+        self.code().parse().ok()
+    }
+
+    /// status is the status code including custom ones, e.g. 499, the status_code by default
+    fn status(&self) -> Status {
+        self.status_code().into()
+    }
+
+    /// pvlost is the pv status of the error, inferred from the status code by default, see `PVLost::infer`
+    fn pvlost(&self) -> PVLost {
+        PVLost::infer(self.status_code())
+    }
+
+    /// description is the long explanation of the error shown in catalogs, empty by default
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// retryable tells whether the failed request may be retried, never by default
+    fn retryable(&self) -> Retryable {
+        Retryable::Never
+    }
+
+    /// retry_after is the hint of how long clients should wait before retrying, None by default
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+
+    /// severity is the level the error is logged at, inferred from status code and pvlost by default, see `Severity::infer`
+    fn severity(&self) -> Severity {
+        let severity = Severity::from_status_code(self.status_code());
+        #[cfg(feature = "pvlost")]
+        let severity = severity.with_pvlost(self.pvlost());
+        severity
+    }
 }
 
 pub trait APIErrorMetas {
@@ -25,11 +67,106 @@ pub struct APIError<'a> {
     pub meta: &'a dyn APIErrorMeta, // TODO: static dispatch with enum_dispatch!
     pub error: anyhow::Error,
     pub caller: Option<&'static str>,
+    /// the errorspace the error was adapted or forced in, None if the APIError is built directly
+    pub errorspace: Option<ErrorspaceName>,
 
     // #[serde(skip_serializing_if = "extensions_is_empty")]
     // pub extensions: Option<Map<String, Value>>, // TODO: add extension support
 }
 
+impl<'a> APIError<'a> {
+    /// public_message returns the message written for clients, which is the message of the meta
+    pub fn public_message(&self) -> &str {
+        self.message()
+    }
+
+    /// internal_detail returns the caller and the whole error chain, for logs only
+    pub fn internal_detail(&self) -> String {
+        match self.caller {
+            Some(caller) => format!("{}->{:#}", caller, self.error),
+            None => format!("{:#}", self.error),
+        }
+    }
+
+    /// is_redacted returns true if the redaction policy of the errorspace of the error hides the error chain,
+    /// see `errorspace_name`
+    pub fn is_redacted(&self) -> bool {
+        redaction_errorspace(self.errorspace_name()).redacts(self)
+    }
+
+    /// detail returns the error chain if the redaction policy of the errorspace of the error exposes it
+    /// 
+    /// Serializers and response converters render this instead of `Display`, which has the caller and is internal.
+    /// Nested APIErrors of the chain, e.g. from `adapt!` over `force!`, are rendered by their public message.
+    pub fn detail(&self) -> Option<String> {
+        if self.is_redacted() {
+            None
+        } else {
+            Some(public_chain(self.error.as_ref()))
+        }
+    }
+
+    /// global_code returns the global code of the error by the codes reserved in the errorspace of the error, see `Errorspace::global_code`
+    pub fn global_code(&self) -> Option<u64> {
+        global_code_errorspace(self.errorspace_name(), self)
+    }
+
+    /// errorspace_name returns the errorspace the error was adapted or forced in, or the current errorspace
+    pub fn errorspace_name(&self) -> ErrorspaceName {
+        self.errorspace.clone().unwrap_or_else(current_errorspace)
+    }
+
+    /// localized_message returns the message in locale registered on the errorspace of the error or its parents,
+    /// falling back to `message()`
+    pub fn localized_message(&self, locale: &str) -> Cow<'_, str> {
+        match resolve_message_errorspace(self.errorspace_name(), self.system(), self.code(), locale) {
+            Some((message, _)) => Cow::Owned(message),
+            None => Cow::Borrowed(self.message()),
+        }
+    }
+
+    /// negotiate_message returns the message in the most preferred language of an `Accept-Language` header having one,
+    /// together with the language of the matched bundle, e.g. `zh` for `zh-CN`, or `message()` and None
+    pub fn negotiate_message(&self, accept_language: &str) -> (Cow<'_, str>, Option<String>) {
+        negotiate_message(self, &self.errorspace_name(), accept_language)
+    }
+}
+
+/// public_chain renders the error chain like `{:#}`, replacing the meta and caller of APIError links with their public message
+fn public_chain(error: &(dyn Error + 'static)) -> String {
+    let mut links = Vec::new();
+    let mut next = Some(error);
+    while let Some(link) = next {
+        match link.downcast_ref::<APIError>() {
+            Some(api_err) => {
+                links.push(api_err.public_message().to_string());
+                // the source of an APIError skips its error, which is the next link
+                next = Some(api_err.error.as_ref());
+            },
+            None => {
+                links.push(link.to_string());
+                next = link.source();
+            },
+        }
+    }
+    links.join(": ")
+}
+
+/// negotiate_message picks the localized message of meta for an `Accept-Language` header in the named errorspace
+/// 
+/// The language is the matched bundle locale in the casing of the header, e.g. `zh` if `zh-CN` falls back to it.
+pub(crate) fn negotiate_message<'m>(meta: &'m dyn APIErrorMeta, errorspace: &str, accept_language: &str) -> (Cow<'m, str>, Option<String>) {
+    for language in parse_accept_language(accept_language) {
+        if let Some((message, locale)) = resolve_message_errorspace(errorspace, meta.system(), meta.code(), &language) {
+            // the bundle locale is a normalized prefix of the language
+            let language = language.trim().get(..locale.len()).map_or(locale.clone(), |tag| tag.replace('_', "-"));
+            return (Cow::Owned(message), Some(language));
+        }
+    }
+    (Cow::Borrowed(meta.message()), None)
+}
+
+// NOTE: Display has the caller and the error chain, it is for logs and must not be sent to clients
 impl<'a> Display for APIError<'a> {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match self.caller{
@@ -47,23 +184,46 @@ impl<'a>  Error for APIError<'a> {
 
 impl<'a>  APIErrorMeta for APIError<'a>  {
     fn system(&self) -> &str {
-        &self.meta.system()
+        self.meta.system()
     }
 
     fn code(&self) -> &str {
-        &self.meta.code()
+        self.meta.code()
     }
 
     fn message(&self) -> &str {
-        &self.meta.message()
+        self.meta.message()
     }
 
     fn status_code(&self) -> StatusCode {
         self.meta.status_code()
     }
 
-    #[cfg(feature = "pvlost")]
+    fn status(&self) -> Status {
+        self.meta.status()
+    }
+
+    fn numeric_code(&self) -> Option<u32> {
+        self.meta.numeric_code()
+    }
+
     fn pvlost(&self) -> PVLost {
         self.meta.pvlost()
     }
+
+    fn description(&self) -> &str {
+        self.meta.description()
+    }
+
+    fn retryable(&self) -> Retryable {
+        self.meta.retryable()
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        self.meta.retry_after()
+    }
+
+    fn severity(&self) -> Severity {
+        self.meta.severity()
+    }
 }
@@ -0,0 +1,229 @@
+//! PV loss accounting, enabled by the `pvlost` feature.
+//!
+//! `Availability` records the `PVLost` of every request in a sliding window of time buckets and
+//! computes availability ratios split by local and remote loss, overall, per system and per code.
+//! Successful requests are recorded with `Builtin::Successful` or any meta with pvlost 0, failed
+//! ones with their `APIError`. Like the module, the example requires the `pvlost` feature:
+//!
+//! ```
+//! use std::time::Duration;
+//! use reskit_apierrors::availability::Availability;
+//! use reskit_apierrors::{force, APIError, Builtin};
+//!
+//! let availability = Availability::new(Duration::from_secs(300), 30);
+//! availability.record(&Builtin::Successful);
+//! let err = force!(anyhow::anyhow!("upstream down"), &Builtin::Unavailable);
+//! let api_err = err.downcast_ref::<APIError>().unwrap();
+//! availability.record(api_err);
+//! let overall = availability.snapshot().overall;
+//! assert_eq!(overall.availability(), 0.5);
+//! assert_eq!(overall.remote_availability(), 0.5);
+//! ```
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{APIErrorMeta, PVLost};
+
+/// Counts are the requests recorded in a window, by pvlost, requests of reasons not counting as lost are successful
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct Counts {
+    pub total: u64,
+    pub successful: u64,
+    pub remote_lost: u64,
+    pub local_lost: u64,
+}
+
+impl Counts {
+    pub fn add(&mut self, pvlost: PVLost) {
+        self.total += 1;
+        if !pvlost.is_lost() {
+            self.successful += 1;
+        } else if pvlost.is_local() {
+            self.local_lost += 1;
+        } else {
+            self.remote_lost += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Counts) {
+        self.total += other.total;
+        self.successful += other.successful;
+        self.remote_lost += other.remote_lost;
+        self.local_lost += other.local_lost;
+    }
+
+    /// availability is the ratio of requests not lost, 1.0 without requests
+    pub fn availability(&self) -> f64 {
+        self.ratio_not(self.remote_lost + self.local_lost)
+    }
+
+    /// remote_availability is the ratio of requests not lost by remote errors, 1.0 without requests
+    pub fn remote_availability(&self) -> f64 {
+        self.ratio_not(self.remote_lost)
+    }
+
+    /// local_availability is the ratio of requests not lost by local errors, 1.0 without requests
+    pub fn local_availability(&self) -> f64 {
+        self.ratio_not(self.local_lost)
+    }
+
+    fn ratio_not(&self, lost: u64) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            1.0 - lost as f64 / self.total as f64
+        }
+    }
+}
+
+/// Snapshot is the aggregation of the requests recorded in the window
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct Snapshot {
+    pub window: Duration,
+    pub overall: Counts,
+    pub by_system: BTreeMap<String, Counts>,
+    /// counts keyed by `system:code`
+    pub by_code: BTreeMap<String, Counts>,
+}
+
+struct Bucket {
+    start: Instant,
+    counts: HashMap<(String, String), Counts>,
+}
+
+/// Availability accumulates the pvlost of requests in a sliding window
+pub struct Availability {
+    window: Duration,
+    width: Duration,
+    buckets: Mutex<VecDeque<Bucket>>,
+}
+
+impl Default for Availability {
+    /// default window of 5 minutes in 30 buckets
+    fn default() -> Availability {
+        Availability::new(Duration::from_secs(300), 30)
+    }
+}
+
+impl Availability {
+    /// new create an accumulator over window, split in buckets, the oldest bucket expires at once
+    pub fn new(window: Duration, buckets: u32) -> Availability {
+        Availability {
+            window,
+            width: window / buckets.max(1),
+            buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// record add a request which ended with meta
+    pub fn record(&self, meta: &dyn APIErrorMeta) {
+        self.record_at(Instant::now(), meta.system(), meta.code(), meta.pvlost());
+    }
+
+    /// record_at add a request of system:code with pvlost ended at now
+    pub fn record_at(&self, now: Instant, system: &str, code: &str, pvlost: PVLost) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        self.expire(&mut buckets, now);
+        let fresh = buckets.back().is_some_and(|bucket| now.saturating_duration_since(bucket.start) < self.width);
+        if !fresh {
+            buckets.push_back(Bucket { start: now, counts: HashMap::new() });
+        }
+        let bucket = buckets.back_mut().unwrap();
+        bucket.counts.entry((system.to_string(), code.to_string())).or_default().add(pvlost);
+    }
+
+    /// snapshot aggregate the requests recorded in the window
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot_at(Instant::now())
+    }
+
+    /// snapshot_at aggregate the requests recorded in the window ending at now
+    pub fn snapshot_at(&self, now: Instant) -> Snapshot {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        self.expire(&mut buckets, now);
+        let mut snapshot = Snapshot { window: self.window, ..Snapshot::default() };
+        for bucket in buckets.iter() {
+            for ((system, code), counts) in &bucket.counts {
+                snapshot.overall.merge(counts);
+                snapshot.by_system.entry(system.clone()).or_default().merge(counts);
+                snapshot.by_code.entry(format!("{}:{}", system, code)).or_default().merge(counts);
+            }
+        }
+        snapshot
+    }
+
+    /// reset drop all recorded requests
+    pub fn reset(&self) {
+        self.buckets.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    fn expire(&self, buckets: &mut VecDeque<Bucket>, now: Instant) {
+        while buckets.front().is_some_and(|bucket| now.saturating_duration_since(bucket.start) >= self.window) {
+            buckets.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::{Builtin, PVLost};
+    use super::{Availability, Counts};
+
+    #[test]
+    fn test_counts() {
+        let mut counts = Counts::default();
+        assert_eq!(counts.availability(), 1.0);
+        for pvlost in [PVLost::Successful, PVLost::Successful, PVLost::RemoteError, PVLost::LocalError] {
+            counts.add(pvlost);
+        }
+        assert_eq!(counts.availability(), 0.5);
+        assert_eq!(counts.remote_availability(), 0.75);
+        assert_eq!(counts.local_availability(), 0.75);
+    }
+
+    #[test]
+    fn test_window() {
+        let availability = Availability::new(Duration::from_secs(60), 6);
+        let start = Instant::now();
+        availability.record_at(start, "", "0", PVLost::Successful);
+        availability.record_at(start + Duration::from_secs(5), "", "26", PVLost::RemoteError);
+        availability.record_at(start + Duration::from_secs(30), "user", "1", PVLost::LocalError);
+        availability.record_at(start + Duration::from_secs(30), "", "0", PVLost::Successful);
+
+        let snapshot = availability.snapshot_at(start + Duration::from_secs(59));
+        assert_eq!(snapshot.overall.total, 4);
+        assert_eq!(snapshot.overall.availability(), 0.5);
+        assert_eq!(snapshot.by_system[""].total, 3);
+        assert_eq!(snapshot.by_system["user"].local_lost, 1);
+        assert_eq!(snapshot.by_code[":0"].successful, 2);
+        assert_eq!(snapshot.by_code[":26"].remote_lost, 1);
+
+        // the first bucket, with the first two requests, expires
+        let snapshot = availability.snapshot_at(start + Duration::from_secs(61));
+        assert_eq!(snapshot.overall.total, 2);
+        assert_eq!(snapshot.overall.remote_availability(), 1.0);
+        assert_eq!(availability.snapshot_at(start + Duration::from_secs(120)).overall, Counts::default());
+    }
+
+    #[test]
+    fn test_record() {
+        let availability = Availability::default();
+        availability.record(&Builtin::Successful);
+        availability.record(&Builtin::Unavailable);
+        let snapshot = availability.snapshot();
+        assert_eq!(snapshot.overall.total, 2);
+        assert_eq!(snapshot.by_code[":26"].remote_lost, 1);
+        availability.reset();
+        assert_eq!(availability.snapshot().overall.total, 0);
+    }
+}
@@ -3,6 +3,35 @@ use std::fmt::Debug;
 use crate::{AsAPIErrorMeta, APIErrorMeta};
 
 /// Builtin defines the builtin api error metas
+///
+/// The codes are stable, downstream catalogs may rely on them, new builtins only take unused codes:
+///
+/// | Code | Builtin | Status Code | `google.rpc.Code` |
+/// |---|---|---|---|
+/// | 0 | Successful | 200 | OK |
+/// | 1 | Unknown | 500 | UNKNOWN |
+/// | 2 | Internal | 500 | INTERNAL |
+/// | 3 | InvalidArgument | 400 | INVALID_ARGUMENT |
+/// | 4 | Unauthorized | 401 | UNAUTHENTICATED |
+/// | 5 | DataSourceFailure | 502 | |
+/// | 6 | NotImplemented | 501 | UNIMPLEMENTED |
+/// | 7 | NotFound | 404 | NOT_FOUND |
+/// | 8 | Conflict | 409 | |
+/// | 9 | PayloadTooLarge | 413 | |
+/// | 10 | UnsupportedMediaType | 415 | |
+/// | 11 | MethodNotAllowed | 405 | |
+/// | 13 | PermissionDenied | 403 | PERMISSION_DENIED |
+/// | 16 | ResourceExhausted | 429 | RESOURCE_EXHAUSTED |
+/// | 20 | FailedPrecondition | 400 | FAILED_PRECONDITION |
+/// | 21 | OutOfRange | 400 | OUT_OF_RANGE |
+/// | 22 | AlreadyExists | 409 | ALREADY_EXISTS |
+/// | 23 | Aborted | 409 | ABORTED |
+/// | 24 | Cancelled | 499 | CANCELLED |
+/// | 25 | DeadlineExceeded | 504 | DEADLINE_EXCEEDED |
+/// | 26 | Unavailable | 503 | UNAVAILABLE |
+/// | 27 | DataLoss | 500 | DATA_LOSS |
+///
+/// Codes 12, 14, 15 and 17 to 19 are reserved.
 #[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
 pub enum Builtin {
     /**
@@ -52,7 +81,7 @@ pub enum Builtin {
     Internal,
 
     /**
-    InvalidArgument 未知的服务端错误，通常是服务端bug
+    InvalidArgument 客户端指定了无效的参数
 
     Mapping:
     - google api style guide: `google.rpc.Code.INVALID_ARGUMENT`
@@ -81,6 +110,17 @@ pub enum Builtin {
     #[apierrormeta(system="", code="4", message="Authentication failed.", status_code=401)]
     Unauthorized,
 
+    /**
+    DataSourceFailure 上游错误
+
+    Mapping:
+    - http status code: 502 Bad Gateway
+
+    A data source or an upstream service the operation depends on failed.
+    */
+    #[apierrormeta(system="", code="5", message="Data source request failure.", status_code=502, pvlost="remote_error")]
+    DataSourceFailure,
+
     /**
     NotImplemented API方法未被服务端实现
 
@@ -98,7 +138,7 @@ pub enum Builtin {
     NotFound 没有找到指定的资源，或者请求被未公开的原因（例如白名单）拒绝
 
     Mapping:
-    - `googole.rpc.Code.NOT_FOUND`: Some requested entity (e.g., file or directory) was not found.
+    - `google.rpc.Code.NOT_FOUND`: Some requested entity (e.g., file or directory) was not found.
     - http status code: 404 Not Found
 
     Note to server developers: if a request is denied for an entire class
@@ -110,6 +150,53 @@ pub enum Builtin {
     #[apierrormeta(system="", code="7", message="Not found.", status_code=404)]
     NotFound,
 
+    /**
+    Conflict 请求与资源的当前状态冲突，例如版本不一致。
+
+    Mapping:
+    - http status code: 409 Conflict
+
+    The request conflicts with the current state of the resource, e.g. an
+    outdated etag or version. Prefer `AlreadyExists` for creations and
+    `Aborted` for concurrency failures which can be retried.
+    */
+    #[apierrormeta(system="", code="8", message="Conflict.", status_code=409)]
+    Conflict,
+
+    /**
+    PayloadTooLarge 请求体超过服务端限制。
+
+    Mapping:
+    - http status code: 413 Payload Too Large
+
+    The request body is larger than the server is willing to process.
+    */
+    #[apierrormeta(system="", code="9", message="Payload too large.", status_code=413)]
+    PayloadTooLarge,
+
+    /**
+    UnsupportedMediaType 请求体的媒体类型不被支持。
+
+    Mapping:
+    - http status code: 415 Unsupported Media Type
+
+    The media type of the request body is not supported by the method.
+    */
+    #[apierrormeta(system="", code="10", message="Unsupported media type.", status_code=415)]
+    UnsupportedMediaType,
+
+    /**
+    MethodNotAllowed 资源不支持请求的HTTP方法。
+
+    Mapping:
+    - http status code: 405 Method Not Allowed
+
+    The HTTP method is known by the server but not supported by the target
+    resource.
+    */
+    #[apierrormeta(system="", code="11", message="Method not allowed.", status_code=405)]
+    MethodNotAllowed,
+
     /**
     PermissionDenied 客户端没有足够的权限。 发生这种情况的原因可能是OAuth令牌没有正确的作用域，客户端没有权限，或者API尚未为客户端项目启用。
 
@@ -129,16 +216,6 @@ pub enum Builtin {
     #[apierrormeta(system="", code="13", message="Permission Denied.", status_code=403)]
     PermissionDenied,
 
-
-    /**
-    DataSourceFailure 上游错误
-
-    Mapping:
-    - http status code: 500 Internal Server Error
-    */
-    //#[apierrormeta(system="", code="13", message="Data source request failure.", status_code=500, pvlost=1)]
-    //DataSourceFailure,
-
     /**
     ResourceExhausted 资源配额不足或达到速率限制。 客户应该查找google.rpc.QuotaFailure错误详细信息以获取更多信息。
 
@@ -149,7 +226,7 @@ pub enum Builtin {
     Some resource has been exhausted, perhaps a per-user quota, or
     perhaps the entire file system is out of space.
     */
-    #[apierrormeta(system="", code="16", message="Data source request failure.", status_code=429)]
+    #[apierrormeta(system="", code="16", message="Resource exhausted.", status_code=429, retryable="safe", retry_after=1)]
     ResourceExhausted,
 
     /**
@@ -231,7 +308,7 @@ pub enum Builtin {
     See the guidelines above for deciding between `FAILED_PRECONDITION`,
     `ABORTED`, and `UNAVAILABLE`.
     */
-    #[apierrormeta(system="", code="23", message="Aborted, retry whole transaction.", status_code=409)]
+    #[apierrormeta(system="", code="23", message="Aborted, retry whole transaction.", status_code=409, retryable="safe")]
     Aborted,
 
     /**
@@ -243,7 +320,7 @@ pub enum Builtin {
 
     The operation was cancelled, typically by the caller.
     */
-    #[apierrormeta(system="", code="24", message="Request cancelled by client.", status_code=400)] // FIXME: 499
+    #[apierrormeta(system="", code="24", message="Request cancelled by client.", status_code=499, pvlost="local_error")]
     Cancelled,
 
     /**
@@ -259,14 +336,14 @@ pub enum Builtin {
     successful response from a server could have been delayed long
     enough for the deadline to expire.
     */
-    #[apierrormeta(system="", code="25", message="Timeout.", status_code=504, pvlost=1)]
+    #[apierrormeta(system="", code="25", message="Timeout.", status_code=504, pvlost=1, retryable="idempotent_only")]
     DeadlineExceeded,
 
     /**
     Unavailable 服务不可用。通常是服务端宕机。通常由网关返回
 
     Mapping:
-    	- `google.rpc.Code.UNAVAILABLE`
+    - `google.rpc.Code.UNAVAILABLE`
     - http status code: 503 Service Unavailable
 
     The service is currently unavailable.  This is most likely a
@@ -277,7 +354,7 @@ pub enum Builtin {
     See the guidelines above for deciding between `FAILED_PRECONDITION`,
     `ABORTED`, and `UNAVAILABLE`.
     */
-    #[apierrormeta(system="", code="26", message="Service unavailable.", status_code=503, pvlost=1)]
+    #[apierrormeta(system="", code="26", message="Service unavailable.", status_code=503, pvlost=1, retryable="idempotent_only", retry_after=1)]
     Unavailable,
 
     /**
@@ -289,13 +366,13 @@ pub enum Builtin {
 
     Unrecoverable data loss or corruption.
     */
-    #[apierrormeta(system="", code="27", message="Data loss.", status_code=503)]
+    #[apierrormeta(system="", code="27", message="Data loss.", status_code=500, severity="critical")]
     DataLoss,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Builtin, APIErrorMeta};
+    use crate::{Builtin, APIErrorMeta, APIErrorMetas};
 
     #[test]
     fn test_meta() {
@@ -303,6 +380,47 @@ mod tests {
         assert_eq!(Builtin::Unknown.message(), "Unknown error.");
     }
 
+    #[test]
+    fn test_description() {
+        assert!(Builtin::Successful.description().starts_with("Successful 请求成功\n\nMapping:\n- google api style guide: `google.rpc.Code.OK`\n"));
+        assert!(Builtin::Successful.description().ends_with("\nNot an error; returned on success"));
+        assert!(Builtin::ResourceExhausted.description().starts_with("ResourceExhausted "));
+    }
+
+    #[test]
+    fn test_codes() {
+        // the codes are stable, never change a pinned one
+        let pinned = [
+            (Builtin::Successful, "0", 200),
+            (Builtin::Unknown, "1", 500),
+            (Builtin::Internal, "2", 500),
+            (Builtin::InvalidArgument, "3", 400),
+            (Builtin::Unauthorized, "4", 401),
+            (Builtin::DataSourceFailure, "5", 502),
+            (Builtin::NotImplemented, "6", 501),
+            (Builtin::NotFound, "7", 404),
+            (Builtin::Conflict, "8", 409),
+            (Builtin::PayloadTooLarge, "9", 413),
+            (Builtin::UnsupportedMediaType, "10", 415),
+            (Builtin::MethodNotAllowed, "11", 405),
+            (Builtin::PermissionDenied, "13", 403),
+            (Builtin::ResourceExhausted, "16", 429),
+            (Builtin::FailedPrecondition, "20", 400),
+            (Builtin::OutOfRange, "21", 400),
+            (Builtin::AlreadyExists, "22", 409),
+            (Builtin::Aborted, "23", 409),
+            (Builtin::Cancelled, "24", 499),
+            (Builtin::DeadlineExceeded, "25", 504),
+            (Builtin::Unavailable, "26", 503),
+            (Builtin::DataLoss, "27", 500),
+        ];
+        assert_eq!(Builtin::api_error_metas().len(), pinned.len());
+        for (meta, code, status_code) in pinned.iter() {
+            assert_eq!(meta.code(), *code, "{:?}", meta);
+            assert_eq!(meta.status(), *status_code, "{:?}", meta);
+        }
+    }
+
     #[test]
     fn test_lifetime() {
         fn enum_as_static(meta: &'static dyn APIErrorMeta) -> &'static dyn APIErrorMeta {
@@ -0,0 +1,67 @@
+use std::sync::PoisonError;
+
+use thiserror::Error;
+
+/// RegistryError describes why an errorspace registry operation failed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RegistryError {
+    /// The named errorspace is not registered
+    #[error("errorspace {0:?} not found")]
+    UnknownErrorspace(String),
+
+    /// The (system, code) pair is not registered in the errorspace
+    #[error("api error meta {system}:{code} not found")]
+    UnknownCode { system: String, code: String },
+
+    /// A thread panicked while holding the registry lock
+    #[error("errorspace registry lock poisoned")]
+    Poisoned,
+
+    /// The named errorspace is already registered
+    #[error("errorspace {0:?} already exists")]
+    Conflict(String),
+
+    /// A different api error meta is registered under the same system:code, raised in strict mode
+    #[error("api error meta {system}:{code} conflicts: {existing} registered, {rejected} rejected")]
+    MetaConflict { system: String, code: String, existing: String, rejected: String },
+
+    /// The code of an api error meta is not a canonical numeric code in the range reserved by its system, raised in strict mode
+    #[error("api error meta {system}:{code} out of the reserved codes {start}..={end}")]
+    CodeOutOfRange { system: String, code: String, start: u32, end: u32 },
+
+    /// The code range can not be reserved by the system
+    #[error("invalid code range of system {system:?}: {reason}")]
+    InvalidCodeRange { system: String, reason: String },
+}
+
+impl<T> From<PoisonError<T>> for RegistryError {
+    fn from(_: PoisonError<T>) -> Self {
+        RegistryError::Poisoned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, Arc};
+    use super::RegistryError;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(RegistryError::UnknownErrorspace("foo".to_string()).to_string(), "errorspace \"foo\" not found");
+        let err = RegistryError::UnknownCode { system: "dummy".to_string(), code: "1".to_string() };
+        assert_eq!(err.to_string(), "api error meta dummy:1 not found");
+        assert_eq!(RegistryError::Conflict("".to_string()).to_string(), "errorspace \"\" already exists");
+    }
+
+    #[test]
+    fn test_from_poison() {
+        let lock = Arc::new(Mutex::new(()));
+        let lock2 = lock.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = lock2.lock().unwrap();
+            panic!("poison");
+        }).join();
+        let err: RegistryError = lock.lock().unwrap_err().into();
+        assert_eq!(err, RegistryError::Poisoned);
+    }
+}
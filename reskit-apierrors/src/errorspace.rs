@@ -1,27 +1,120 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
 
-use crate::{APIErrorMeta, APIError};
+use http_types::StatusCode;
 
-#[derive(Clone)]
+use crate::{APIErrorMeta, APIError, ErrorspaceName, RegistryError};
+use crate::validate::{Report, Validator};
+use crate::locale::{MessageBundle, normalize_locale, fallback_chain};
+use crate::redaction::RedactionPolicy;
+
+#[cfg(feature = "pvlost")]
+use crate::PVLost;
+
+#[derive(Clone, Default)]
 pub struct Errorspace<'a> {
     errors: HashMap<&'a str, HashMap<&'a str, &'a dyn APIErrorMeta>>,
+    parent: Option<ErrorspaceName>,
+    messages: HashMap<String, MessageBundle>,
+    redaction: Option<RedactionPolicy>,
+    ranges: HashMap<String, CodeRange>,
+    /// the system reserving each prefix, indexes the global codes
+    prefixes: HashMap<u32, String>,
 }
 
 impl<'a> Errorspace<'a> {
     pub fn new() -> Errorspace<'a> {
-        Errorspace { errors: HashMap::new() }
+        Errorspace::default()
+    }
+
+    /// with_parent create an empty errorspace layered on the named errorspace
+    /// 
+    /// The global registry falls back to the parent for every system:code the child does not register,
+    /// so the child only overrides a subset, e.g. status codes rebound for one partner.
+    pub fn with_parent(parent: impl Into<ErrorspaceName>) -> Errorspace<'a> {
+        Errorspace { parent: Some(parent.into()), ..Errorspace::default() }
+    }
+
+    /// parent returns the name of the parent errorspace, if any
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    /// set_parent link the errorspace to another parent or detach it
+    pub fn set_parent(&mut self, parent: Option<ErrorspaceName>) {
+        self.parent = parent;
     }
 
     /// register_api_error_class register api error meta, if exists then ignore
-    pub fn register_api_error_meta(&mut self, meta: &'a dyn APIErrorMeta) {
-        let system = self.errors.entry(meta.system()).or_insert(HashMap::new());
-        system.entry(meta.code()).or_insert(meta);
+    /// 
+    /// Returns whether the meta was inserted, or ignored as an identical or conflicting duplicate.
+    pub fn register_api_error_meta(&mut self, meta: &'a dyn APIErrorMeta) -> Registration<'a> {
+        if let Some(registration) = self.check_code_range(meta) {
+            return registration;
+        }
+        let system = self.errors.entry(meta.system()).or_default();
+        match system.get(meta.code()) {
+            None => {
+                system.insert(meta.code(), meta);
+                Registration::Inserted
+            },
+            Some(&existing) => Registration::duplicate(existing, meta),
+        }
+    }
+
+    /// check_api_error_meta returns what register_api_error_meta would do without registering
+    pub fn check_api_error_meta(&self, meta: &'a dyn APIErrorMeta) -> Registration<'a> {
+        if let Some(registration) = self.check_code_range(meta) {
+            return registration;
+        }
+        match self.get_api_error_meta(meta.system(), meta.code()) {
+            None => Registration::Inserted,
+            Some(existing) => Registration::duplicate(existing, meta),
+        }
+    }
+
+    /// check_api_error_metas returns what registering the metas in order would do without registering,
+    /// the metas are checked against each other too
+    pub fn check_api_error_metas(&self, metas: &[&'a dyn APIErrorMeta]) -> Vec<Registration<'a>> {
+        let mut batch: HashMap<(&str, &str), &'a dyn APIErrorMeta> = HashMap::new();
+        metas.iter().map(|&meta| {
+            let registration = match batch.get(&(meta.system(), meta.code())) {
+                Some(&existing) => Registration::duplicate(existing, meta),
+                None => self.check_api_error_meta(meta),
+            };
+            if let Registration::Inserted = registration {
+                batch.insert((meta.system(), meta.code()), meta);
+            }
+            registration
+        }).collect()
     }
 
     /// overwrite_api_error_class overwrite existing api error meta, used for stauts code rebinding
-    pub fn overwrite_api_error_meta(&mut self, meta: &'a dyn APIErrorMeta) {
-        let system = self.errors.entry(meta.system()).or_insert(HashMap::new());
-        system.insert(meta.code(), meta);
+    /// 
+    /// Returns the replaced meta, if any.
+    pub fn overwrite_api_error_meta(&mut self, meta: &'a dyn APIErrorMeta) -> Option<&'a dyn APIErrorMeta> {
+        let system = self.errors.entry(meta.system()).or_default();
+        system.insert(meta.code(), meta)
+    }
+
+    /// unregister_api_error_meta remove the api error meta of system:code, returns the removed meta
+    pub fn unregister_api_error_meta(&mut self, system: &str, code: &str) -> Option<&'a dyn APIErrorMeta> {
+        let app = self.errors.get_mut(system)?;
+        let meta = app.remove(code);
+        if app.is_empty() {
+            self.errors.remove(system);
+        }
+        meta
+    }
+
+    /// remove_system remove all api error metas of the system, returns the removed metas
+    pub fn remove_system(&mut self, system: &str) -> Vec<&'a dyn APIErrorMeta> {
+        match self.errors.remove(system) {
+            Some(app) => app.into_values().collect(),
+            None => Vec::new(),
+        }
     }
 
     pub fn get_api_error_meta(&self, system: &str, code: &str) -> Option<&'a dyn APIErrorMeta> {
@@ -38,32 +131,211 @@ impl<'a> Errorspace<'a> {
         }
     }
 
+    /// systems iterate over the registered systems in sorted order
+    pub fn systems(&self) -> impl Iterator<Item = &'a str> {
+        let mut systems: Vec<&'a str> = self.errors.keys().copied().collect();
+        systems.sort_unstable();
+        systems.into_iter()
+    }
+
+    /// iter iterate over all registered api error metas sorted by system then code, numeric codes in numeric order
+    pub fn iter(&self) -> impl Iterator<Item = &'a dyn APIErrorMeta> {
+        let mut metas: Vec<&'a dyn APIErrorMeta> = self.errors.values().flat_map(|app| app.values().copied()).collect();
+        metas.sort_by(|a, b| a.system().cmp(b.system()).then_with(|| cmp_code(a.code(), b.code())));
+        metas.into_iter()
+    }
+
+    /// iter_system iterate over the api error metas of the system sorted by code
+    pub fn iter_system(&self, system: &str) -> impl Iterator<Item = &'a dyn APIErrorMeta> {
+        let mut metas: Vec<&'a dyn APIErrorMeta> = match self.errors.get(system) {
+            Some(app) => app.values().copied().collect(),
+            None => Vec::new(),
+        };
+        metas.sort_by(|a, b| cmp_code(a.code(), b.code()));
+        metas.into_iter()
+    }
+
+    /// filter_by_status_code iterate over the api error metas bound to status code
+    pub fn filter_by_status_code(&self, status_code: StatusCode) -> impl Iterator<Item = &'a dyn APIErrorMeta> {
+        self.iter().filter(move |meta| meta.status_code() == status_code)
+    }
+
+    /// filter_by_pvlost iterate over the api error metas with the pvlost
+    #[cfg(feature = "pvlost")]
+    pub fn filter_by_pvlost(&self, pvlost: PVLost) -> impl Iterator<Item = &'a dyn APIErrorMeta> {
+        self.iter().filter(move |meta| meta.pvlost() == pvlost)
+    }
+
+    /// summary count the registered api error metas
+    pub fn summary(&self) -> Summary<'a> {
+        let mut summary = Summary::default();
+        for meta in self.iter() {
+            summary.metas += 1;
+            *summary.by_system.entry(meta.system()).or_default() += 1;
+            *summary.by_status_code.entry(meta.status().code()).or_default() += 1;
+            #[cfg(feature = "pvlost")]
+            {
+                *summary.by_pvlost.entry(meta.pvlost().into()).or_default() += 1;
+            }
+        }
+        summary.systems = summary.by_system.len();
+        summary
+    }
+
+    /// register_messages add the localized messages of locale, merging with the registered bundle of the same locale
+    pub fn register_messages(&mut self, locale: &str, bundle: MessageBundle) {
+        self.messages.entry(normalize_locale(locale)).or_default().extend(bundle);
+    }
+
+    /// bundles iterate over the registered locales, normalized, and their message bundles
+    pub fn bundles(&self) -> impl Iterator<Item = (&str, &MessageBundle)> {
+        self.messages.iter().map(|(locale, bundle)| (locale.as_str(), bundle))
+    }
+
+    /// get_message get the message of system:code registered for exactly the locale
+    pub fn get_message(&self, locale: &str, system: &str, code: &str) -> Option<&str> {
+        self.messages.get(&normalize_locale(locale))?.get(system, code)
+    }
+
+    /// localized_message get the message of system:code for the locale or its less specific parents, e.g. zh-CN then zh
+    pub fn localized_message(&self, system: &str, code: &str, locale: &str) -> Option<&str> {
+        fallback_chain(locale)
+            .iter()
+            .find_map(|locale| self.messages.get(locale)?.get(system, code))
+    }
+
+    /// redaction returns the redaction policy set on the errorspace, None if it inherits the policy of the parent
+    pub fn redaction(&self) -> Option<RedactionPolicy> {
+        self.redaction
+    }
+
+    /// set_redaction set the redaction policy of the errorspace, None to inherit the policy of the parent
+    pub fn set_redaction(&mut self, policy: Option<RedactionPolicy>) {
+        self.redaction = policy;
+    }
+
+    /// reserve_codes reserve the numeric codes of range for system, metas of the system out of the range are rejected
+    /// 
+    /// Codes should be canonical, i.e. without sign or leading zeros, so "01" is rejected and can not collide
+    /// with "1". Fails if the prefix is reserved by another system, the range does not fit below `GLOBAL_CODE_BASE`
+    /// or a registered meta of the system is out of the range. Reserving again replaces the range of the system.
+    pub fn reserve_codes(&mut self, system: &str, range: CodeRange) -> Result<(), RegistryError> {
+        let invalid = |reason: String| RegistryError::InvalidCodeRange { system: system.to_string(), reason };
+        if range.start() > range.end() || u64::from(range.end()) >= GLOBAL_CODE_BASE {
+            return Err(invalid(format!("codes {}..={} should be in 0..{}", range.start(), range.end(), GLOBAL_CODE_BASE)));
+        }
+        if let Some(other) = self.prefixes.get(&range.prefix()).filter(|other| *other != system) {
+            return Err(invalid(format!("prefix {} is reserved by {:?}", range.prefix(), other)));
+        }
+        if let Some(meta) = self.iter_system(system).find(|meta| !range.contains_meta(*meta)) {
+            return Err(invalid(format!("code {} is registered", meta.code())));
+        }
+        self.set_code_range(system, range);
+        Ok(())
+    }
+
+    /// code_range returns the codes reserved by system, if any
+    pub fn code_range(&self, system: &str) -> Option<CodeRange> {
+        self.ranges.get(system).copied()
+    }
+
+    /// code_ranges iterate over the systems reserving codes and their ranges
+    pub fn code_ranges(&self) -> impl Iterator<Item = (&str, CodeRange)> {
+        self.ranges.iter().map(|(system, range)| (system.as_str(), *range))
+    }
+
+    /// global_code returns the numeric code unique across the systems, prefix * `GLOBAL_CODE_BASE` + code
+    /// 
+    /// None if the system of meta reserves no codes or the code is not numeric.
+    pub fn global_code(&self, meta: &dyn APIErrorMeta) -> Option<u64> {
+        let range = self.code_range(meta.system())?;
+        let code = range.code_of(meta)?;
+        Some(u64::from(range.prefix()) * GLOBAL_CODE_BASE + u64::from(code))
+    }
+
+    /// get_by_global_code get the api error meta of the global code, see `global_code`
+    pub fn get_by_global_code(&self, global_code: u64) -> Option<&'a dyn APIErrorMeta> {
+        let prefix = u32::try_from(global_code / GLOBAL_CODE_BASE).ok()?;
+        let system = self.prefixes.get(&prefix)?;
+        let range = self.code_range(system)?;
+        let meta = self.get_api_error_meta(system, &(global_code % GLOBAL_CODE_BASE).to_string())?;
+        range.contains_meta(meta).then_some(meta)
+    }
+
+    /// set_code_range set the range of system without checking it, the prefix is indexed to system
+    pub(crate) fn set_code_range(&mut self, system: &str, range: CodeRange) {
+        if let Some(replaced) = self.ranges.insert(system.to_string(), range) {
+            if replaced.prefix() != range.prefix() && self.prefixes.get(&replaced.prefix()).is_some_and(|other| other == system) {
+                self.prefixes.remove(&replaced.prefix());
+            }
+        }
+        self.prefixes.insert(range.prefix(), system.to_string());
+    }
+
+    fn check_code_range(&self, meta: &'a dyn APIErrorMeta) -> Option<Registration<'a>> {
+        let range = self.code_range(meta.system())?;
+        if range.contains_meta(meta) {
+            None
+        } else {
+            Some(Registration::OutOfRange { rejected: meta, range })
+        }
+    }
+
+    /// validate check the registered api error metas with the builtin rules, exact system:code duplicates
+    /// were dropped at registration, see `validate::UniqueCodes`
+    pub fn validate(&self) -> Report {
+        self.validate_with(&Validator::default())
+    }
+
+    /// validate_with check the registered api error metas with the rules of validator
+    pub fn validate_with(&self, validator: &Validator) -> Report {
+        validator.validate(&self.iter().collect::<Vec<_>>())
+    }
+
+    /// try_adapt_meta returns the meta adapt would wrap the anyhow::Error with, fails if it is an APIError unknown to this errorspace
+    pub fn try_adapt_meta(&self, err: &anyhow::Error, default_meta: &'a dyn APIErrorMeta) -> Result<&'a dyn APIErrorMeta, RegistryError> {
+        match err.downcast_ref::<APIError>() {
+            Some(ae) => self.get_api_error_meta(ae.system(), ae.code()).ok_or_else(|| RegistryError::UnknownCode {
+                system: ae.system().to_string(),
+                code: ae.code().to_string(),
+            }),
+            None => Ok(default_meta),
+        }
+    }
+
     /// adapt adapts anyhow::Error to specify error space, or wrap it with default_meta as a APIError
-    pub(crate) fn adapt(&self, 
+    /// 
+    /// An APIError whose system:code is unknown to this errorspace is wrapped with default_meta too.
+    /// The parent is not consulted, use `globals::adapt_errorspace` for layered lookups.
+    pub fn adapt(&self, 
         err: anyhow::Error, 
-        default_meta: &'static dyn APIErrorMeta, 
+        default_meta: &'a dyn APIErrorMeta, 
         mapping_names: &[&str],
         caller: Option<&'static str>,
     ) -> APIError<'a> {
-        let api_err: APIError;
-        if let Some(ae) = err.downcast_ref::<APIError>() {
-            let meta = self.get_api_error_meta(ae.system(), ae.code());
-            api_err = APIError {
-                meta: meta.unwrap(),
-                error: err,
-                caller,
-            }
-        } else {
-            // FIXME: do we need verbose gate here?
-            api_err = APIError {
-                meta: default_meta,
-                error: err,
-                caller,
-            }
-        }
+        // FIXME: do we need verbose gate here?
+        let meta = self.try_adapt_meta(&err, default_meta).unwrap_or(default_meta);
         // TODO: map to other error spaces
         dbg!(mapping_names);
-        api_err
+        APIError {
+            meta,
+            error: err,
+            caller,
+            errorspace: None,
+        }
+    }
+
+    /// try_adapt is the fallible version of adapt, the anyhow::Error is handed back on failure
+    pub fn try_adapt(&self, 
+        err: anyhow::Error, 
+        default_meta: &'a dyn APIErrorMeta, 
+        mapping_names: &[&str],
+        caller: Option<&'static str>,
+    ) -> Result<APIError<'a>, (RegistryError, anyhow::Error)> {
+        match self.try_adapt_meta(&err, default_meta) {
+            Ok(meta) => Ok(self.force(err, meta, mapping_names, caller)),
+            Err(registry_err) => Err((registry_err, err)),
+        }
     }
 
     /// force wraps the anyhow::Error with given meta as a APIError
@@ -78,6 +350,7 @@ impl<'a> Errorspace<'a> {
             meta,
             error: err,
             caller,
+            errorspace: None,
         }
     }
 
@@ -88,12 +361,139 @@ impl<'a> Errorspace<'a> {
     }
 }
 
+/// Registration is the outcome of registering an api error meta
+#[derive(Clone, Copy, Debug)]
+pub enum Registration<'a> {
+    /// the meta is registered
+    Inserted,
+    /// an identical meta is registered under system:code already, the meta is ignored
+    Identical,
+    /// a different meta is registered under system:code already, the meta is rejected
+    Conflict {
+        existing: &'a dyn APIErrorMeta,
+        rejected: &'a dyn APIErrorMeta,
+    },
+    /// the code is not a canonical numeric code in the range reserved by the system, the meta is rejected
+    OutOfRange {
+        rejected: &'a dyn APIErrorMeta,
+        range: CodeRange,
+    },
+}
+
+impl<'a> Registration<'a> {
+    fn duplicate(existing: &'a dyn APIErrorMeta, rejected: &'a dyn APIErrorMeta) -> Registration<'a> {
+        if identical(existing, rejected) {
+            Registration::Identical
+        } else {
+            Registration::Conflict { existing, rejected }
+        }
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, Registration::Conflict { .. })
+    }
+
+    /// is_rejected returns true if the meta is not registered because of a conflict or its code
+    pub fn is_rejected(&self) -> bool {
+        matches!(self, Registration::Conflict { .. } | Registration::OutOfRange { .. })
+    }
+}
+
+/// GLOBAL_CODE_BASE is the multiplier of system prefixes in global codes, reserved codes are below it
+pub const GLOBAL_CODE_BASE: u64 = 10_000;
+
+/// CodeRange is the numeric codes reserved by a system and the prefix of its global codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct CodeRange {
+    prefix: u32,
+    start: u32,
+    end: u32,
+}
+
+impl CodeRange {
+    /// new create the range of codes, the global codes of the system are prefix * `GLOBAL_CODE_BASE` + code
+    pub fn new(prefix: u32, codes: RangeInclusive<u32>) -> CodeRange {
+        CodeRange { prefix, start: *codes.start(), end: *codes.end() }
+    }
+
+    pub fn prefix(&self) -> u32 {
+        self.prefix
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    pub fn contains(&self, code: u32) -> bool {
+        (self.start..=self.end).contains(&code)
+    }
+
+    /// code_of returns the numeric code of meta if it is canonical and in the range, e.g. "1" but not "01"
+    pub fn code_of(&self, meta: &dyn APIErrorMeta) -> Option<u32> {
+        meta.numeric_code().filter(|code| self.contains(*code) && code.to_string() == meta.code())
+    }
+
+    /// contains_meta returns true if the code of meta is canonical numeric and in the range
+    pub fn contains_meta(&self, meta: &dyn APIErrorMeta) -> bool {
+        self.code_of(meta).is_some()
+    }
+}
+
+/// identical compares every attribute of two api error metas
+fn identical(a: &dyn APIErrorMeta, b: &dyn APIErrorMeta) -> bool {
+    let same = a.system() == b.system()
+        && a.code() == b.code()
+        && a.message() == b.message()
+        && a.status() == b.status()
+        && a.retryable() == b.retryable()
+        && a.retry_after() == b.retry_after()
+        && a.severity() == b.severity();
+    #[cfg(feature = "pvlost")]
+    let same = same && a.pvlost() == b.pvlost();
+    same
+}
+
+/// Summary counts the api error metas of an errorspace
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct Summary<'a> {
+    /// number of systems
+    pub systems: usize,
+    /// number of api error metas
+    pub metas: usize,
+    /// number of api error metas per system
+    pub by_system: BTreeMap<&'a str, usize>,
+    /// number of api error metas per status code
+    pub by_status_code: BTreeMap<u16, usize>,
+    /// number of api error metas per pvlost code
+    #[cfg(feature = "pvlost")]
+    pub by_pvlost: BTreeMap<u8, usize>,
+}
+
+/// cmp_code orders numeric codes numerically before non numeric ones, which are ordered lexically
+fn cmp_code(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(x), Ok(y)) => x.cmp(&y).then_with(|| a.cmp(b)),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use http_types::StatusCode;
     use reskit_utils::init_once;
     use anyhow::{anyhow, Result, Context};
-    use crate::{ERRORSPACES, Builtin, adapt, force, AsAPIErrorMeta, prelude::*};
+    use crate::locale::MessageBundle;
+    use crate::{ERRORSPACES, Errorspace, Registration, Builtin, CodeRange, RegistryError, APIError, adapt, force, AsAPIErrorMeta, prelude::*};
 
     #[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
     enum Test {
@@ -105,6 +505,20 @@ mod tests {
         Rebind2,
         #[apierrormeta(system="dummy_clone", code="1", message="dummy error", status_code=500)]
         DummyClone,
+        #[apierrormeta(system="dummy_remove", code="1", message="dummy error", status_code=500)]
+        DummyRemove1,
+        #[apierrormeta(system="dummy_remove", code="2", message="dummy error", status_code=500)]
+        DummyRemove2,
+        #[apierrormeta(system="dummy_remove", code="2", message="dummy error", status_code=500)]
+        DummyRemove2Again,
+        #[apierrormeta(system="ranged", code=1001, message="ranged error", status_code=500)]
+        Ranged,
+        #[apierrormeta(system="ranged", code="2001", message="ranged error", status_code=500)]
+        RangedOut,
+        #[apierrormeta(system="ranged", code="abc", message="ranged error", status_code=500)]
+        RangedText,
+        #[apierrormeta(system="ranged", code="01001", message="ranged error", status_code=500)]
+        RangedPadded,
     }
 
     #[test]
@@ -125,6 +539,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_clone() {
         init_once();
         let mut spaces = ERRORSPACES.write().unwrap();
@@ -144,11 +559,126 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_remove() {
+        let mut space = Errorspace::new();
+        assert!(space.overwrite_api_error_meta(&Test::Dummy).is_none());
+        assert_eq!(space.overwrite_api_error_meta(&Test::Rebind).unwrap().status_code(), StatusCode::InternalServerError);
+        space.register_api_error_meta(&Test::DummyRemove1);
+        space.register_api_error_meta(&Test::DummyRemove2);
+        assert!(space.unregister_api_error_meta("dummy", "2").is_none());
+        assert_eq!(space.unregister_api_error_meta("dummy", "1").unwrap().status_code(), StatusCode::Ok);
+        assert_eq!(space.len("dummy"), 0);
+        assert!(space.unregister_api_error_meta("dummy", "1").is_none());
+        assert_eq!(space.remove_system("dummy_remove").len(), 2);
+        assert!(space.remove_system("dummy_remove").is_empty());
+        assert_eq!(space.len("dummy_remove"), 0);
+    }
+
+    #[test]
+    fn test_introspection() {
+        let mut space = Errorspace::new();
+        for meta in Builtin::api_error_metas() {
+            space.register_api_error_meta(meta);
+        }
+        space.register_api_error_meta(&Test::DummyRemove2);
+        space.register_api_error_meta(&Test::DummyRemove1);
+        space.register_api_error_meta(&Test::DummyClone);
+        assert_eq!(space.systems().collect::<Vec<_>>(), vec!["", "dummy_clone", "dummy_remove"]);
+        let codes: Vec<_> = space.iter().map(|meta| format!("{}:{}", meta.system(), meta.code())).collect();
+        assert_eq!(codes[..4], [":0", ":1", ":2", ":3"]);
+        assert_eq!(codes[codes.len() - 3..], ["dummy_clone:1", "dummy_remove:1", "dummy_remove:2"]);
+        assert_eq!(space.iter_system("dummy_remove").map(|meta| meta.code()).collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(space.iter_system("not_exists").count(), 0);
+        let conflicts: Vec<_> = space.filter_by_status_code(StatusCode::Conflict).map(|meta| meta.code()).collect();
+        assert_eq!(conflicts, vec!["8", "22", "23"]);
+
+        let summary = space.summary();
+        assert_eq!(summary.systems, 3);
+        assert_eq!(summary.metas, Builtin::api_error_metas().len() + 3);
+        assert_eq!(summary.by_system["dummy_remove"], 2);
+        assert_eq!(summary.by_status_code[&409], 3);
+        assert_eq!(summary.by_status_code[&500], 3 + 3);
+    }
+
+    #[test]
+    fn test_registration() {
+        let mut space = Errorspace::new();
+        assert!(matches!(space.check_api_error_meta(&Test::Dummy), Registration::Inserted));
+        assert!(matches!(space.register_api_error_meta(&Test::Dummy), Registration::Inserted));
+        assert!(matches!(space.register_api_error_meta(&Test::Dummy), Registration::Identical));
+        match space.register_api_error_meta(&Test::Rebind) {
+            Registration::Conflict { existing, rejected } => {
+                assert_eq!(existing.status_code(), StatusCode::InternalServerError);
+                assert_eq!(rejected.status_code(), StatusCode::Ok);
+            },
+            registration => panic!("expected conflict, got {:?}", registration),
+        }
+        assert!(space.check_api_error_meta(&Test::Rebind2).is_conflict());
+        assert_eq!(space.get_api_error_meta("dummy", "1").unwrap().status_code(), StatusCode::InternalServerError);
+        space.register_api_error_meta(&Test::DummyRemove2);
+        assert!(matches!(space.register_api_error_meta(&Test::DummyRemove2Again), Registration::Identical));
+    }
+
+    #[test]
+    fn test_code_range() {
+        let mut space = Errorspace::new();
+        space.register_api_error_meta(&Test::RangedOut);
+        assert!(matches!(space.reserve_codes("ranged", CodeRange::new(12, 1000..=1999)), Err(RegistryError::InvalidCodeRange { .. })));
+        space.unregister_api_error_meta("ranged", "2001");
+        space.reserve_codes("ranged", CodeRange::new(12, 1000..=1999)).unwrap();
+        assert!(space.reserve_codes("other", CodeRange::new(12, 0..=99)).is_err());
+        assert!(space.reserve_codes("other", CodeRange::new(13, 0..=10_000)).is_err());
+        space.reserve_codes("", CodeRange::new(0, 0..=99)).unwrap();
+
+        assert_eq!(Test::Ranged.numeric_code(), Some(1001));
+        assert!(matches!(space.register_api_error_meta(&Test::Ranged), Registration::Inserted));
+        assert!(matches!(space.check_api_error_meta(&Test::RangedOut), Registration::OutOfRange { .. }));
+        assert!(space.register_api_error_meta(&Test::RangedText).is_rejected());
+        assert!(matches!(space.register_api_error_meta(&Test::RangedPadded), Registration::OutOfRange { .. }));
+        assert_eq!(space.len("ranged"), 1);
+
+        space.register_api_error_meta(&Builtin::NotFound);
+        assert_eq!(space.global_code(&Test::Ranged), Some(121_001));
+        assert_eq!(space.global_code(&Builtin::NotFound), Some(7));
+        assert_eq!(space.global_code(&Test::Dummy), None);
+        assert_eq!(space.get_by_global_code(121_001).unwrap().code(), "1001");
+        assert_eq!(space.get_by_global_code(7).unwrap().code(), "7");
+        assert!(space.get_by_global_code(131_001).is_none());
+        assert!(space.get_by_global_code(122_001).is_none());
+        assert!(space.get_by_global_code(u64::MAX).is_none());
+
+        space.overwrite_api_error_meta(&Test::RangedPadded);
+        assert_eq!(space.global_code(&Test::RangedPadded), None);
+        assert!(matches!(space.reserve_codes("ranged", CodeRange::new(14, 1000..=1999)), Err(RegistryError::InvalidCodeRange { .. })));
+        space.unregister_api_error_meta("ranged", "01001");
+        space.reserve_codes("ranged", CodeRange::new(14, 1000..=1999)).unwrap();
+        assert!(space.get_by_global_code(121_001).is_none());
+        assert_eq!(space.get_by_global_code(141_001).unwrap().code(), "1001");
+        space.reserve_codes("other", CodeRange::new(12, 0..=99)).unwrap();
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut space = Errorspace::new();
+        space.register_messages("zh", MessageBundle::new().with("", "1", "未知错误。"));
+        space.register_messages("zh_CN", MessageBundle::new().with("", "2", "服务内部错误。"));
+        space.register_messages("zh-cn", MessageBundle::new().with("", "3", "参数错误。"));
+        assert_eq!(space.get_message("zh-CN", "", "2"), Some("服务内部错误。"));
+        assert_eq!(space.get_message("zh-CN", "", "1"), None);
+        assert_eq!(space.localized_message("", "1", "zh-CN"), Some("未知错误。"));
+        assert_eq!(space.localized_message("", "3", "zh-CN"), Some("参数错误。"));
+        assert_eq!(space.localized_message("", "3", "zh"), None);
+        assert_eq!(space.localized_message("", "1", "en"), None);
+        assert_eq!(space.bundles().count(), 2);
+    }
+
     fn demo() -> Result<()>{
         Err(anyhow!("demo error"))
     }
 
     #[test]
+    #[allow(clippy::single_match)]
     fn test_adapt() {
         init_once();
         let result = demo()
@@ -179,6 +709,20 @@ mod tests {
     }
 
     #[test]
+    fn test_try_adapt() {
+        let mut space = Errorspace::default();
+        space.register_api_error_meta(&Test::Dummy);
+        let inner = || force!(anyhow!("xxx"), &Test::DummyClone);
+        let (registry_err, err) = space.try_adapt(inner(), &Builtin::Unknown, &[], None).unwrap_err();
+        assert_eq!(registry_err, RegistryError::UnknownCode { system: "dummy_clone".to_string(), code: "1".to_string() });
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().system(), "dummy_clone");
+        let api_err = space.try_adapt(force!(anyhow!("xxx"), &Test::Dummy), &Builtin::Unknown, &[], None).unwrap();
+        assert_eq!(api_err.system(), "dummy");
+        assert_eq!(space.try_adapt(anyhow!("xxx"), &Builtin::Unknown, &[], None).unwrap().code(), "1");
+    }
+
+    #[test]
+    #[allow(clippy::single_match)]
     fn test_force() {
         init_once();
         let result = demo()
@@ -0,0 +1,222 @@
+//! Error catalog export.
+//!
+//! A `Catalog` is a snapshot of the api error metas of an errorspace grouped by system, rendered
+//! to JSON/YAML for machines and to Markdown/HTML tables for human docs.
+use std::fmt::Write;
+
+use crate::{APIErrorMeta, Errorspace};
+
+/// Entry is the exported form of an api error meta
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct Entry<'a> {
+    pub system: &'a str,
+    pub code: &'a str,
+    pub message: &'a str,
+    pub status_code: u16,
+    #[cfg(feature = "pvlost")]
+    pub pvlost: u8,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "str::is_empty"))]
+    pub description: &'a str,
+}
+
+impl<'a> Entry<'a> {
+    pub fn new(meta: &'a dyn APIErrorMeta) -> Entry<'a> {
+        Entry {
+            system: meta.system(),
+            code: meta.code(),
+            message: meta.message(),
+            status_code: meta.status().code(),
+            #[cfg(feature = "pvlost")]
+            pvlost: meta.pvlost().into(),
+            description: meta.description().trim(),
+        }
+    }
+}
+
+/// System groups the entries of a system sorted by code
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct System<'a> {
+    pub system: &'a str,
+    pub errors: Vec<Entry<'a>>,
+}
+
+/// Catalog is the exported form of an errorspace
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate"))]
+pub struct Catalog<'a> {
+    pub systems: Vec<System<'a>>,
+}
+
+impl<'a> Catalog<'a> {
+    /// new export the api error metas registered in the errorspace, parents are not included
+    ///
+    /// Use `globals::effective_errorspace` to export a layered errorspace with its ancestors.
+    pub fn new(space: &Errorspace<'a>) -> Catalog<'a> {
+        Catalog {
+            systems: space
+                .systems()
+                .map(|system| System {
+                    system,
+                    errors: space.iter_system(system).map(Entry::new).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+
+    /// to_markdown render a table per system
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for system in &self.systems {
+            writeln!(out, "## {}\n", system_title(system.system)).unwrap();
+            #[cfg(feature = "pvlost")]
+            out.push_str("| Code | Message | Status Code | PVLost | Description |\n|---|---|---|---|---|\n");
+            #[cfg(not(feature = "pvlost"))]
+            out.push_str("| Code | Message | Status Code | Description |\n|---|---|---|---|\n");
+            for entry in &system.errors {
+                write!(out, "| {} | {} | {} ", markdown_cell(entry.code), markdown_cell(entry.message), entry.status_code).unwrap();
+                #[cfg(feature = "pvlost")]
+                write!(out, "| {} ", entry.pvlost).unwrap();
+                writeln!(out, "| {} |", markdown_cell(entry.description)).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// to_html render a table per system
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        for system in &self.systems {
+            writeln!(out, "<h2>{}</h2>", html_escape(&system_title(system.system))).unwrap();
+            out.push_str("<table>\n<thead><tr><th>Code</th><th>Message</th><th>Status Code</th>");
+            #[cfg(feature = "pvlost")]
+            out.push_str("<th>PVLost</th>");
+            out.push_str("<th>Description</th></tr></thead>\n<tbody>\n");
+            for entry in &system.errors {
+                write!(out, "<tr><td>{}</td><td>{}</td><td>{}</td>", html_escape(entry.code), html_escape(entry.message), entry.status_code).unwrap();
+                #[cfg(feature = "pvlost")]
+                write!(out, "<td>{}</td>", entry.pvlost).unwrap();
+                writeln!(out, "<td>{}</td></tr>", html_escape(entry.description).replace('\n', "<br>")).unwrap();
+            }
+            out.push_str("</tbody>\n</table>\n");
+        }
+        out
+    }
+}
+
+impl<'a> Errorspace<'a> {
+    /// catalog export the registered api error metas, see `export::Catalog`
+    pub fn catalog(&self) -> Catalog<'a> {
+        Catalog::new(self)
+    }
+}
+
+fn system_title(system: &str) -> String {
+    if system.is_empty() {
+        "(global)".to_string()
+    } else {
+        system.to_string()
+    }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Errorspace, AsAPIErrorMeta, prelude::*};
+
+    #[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+    enum Doc {
+        #[apierrormeta(system="doc", code="10", message="A | B.", status_code=400, description="\n    <Long> explanation.\n    Second line.\n")]
+        Documented,
+        #[apierrormeta(system="doc", code="9", message="Plain.", status_code=500)]
+        Plain,
+    }
+
+    fn space() -> Errorspace<'static> {
+        let mut space = Errorspace::new();
+        space.register_api_error_meta(&Doc::Documented);
+        space.register_api_error_meta(&Doc::Plain);
+        space.register_api_error_meta(&crate::Builtin::Successful);
+        space
+    }
+
+    #[test]
+    fn test_catalog() {
+        let catalog = space().catalog();
+        assert_eq!(catalog.systems.len(), 2);
+        assert_eq!(catalog.systems[0].system, "");
+        assert_eq!(catalog.systems[1].errors.iter().map(|e| e.code).collect::<Vec<_>>(), vec!["9", "10"]);
+        assert_eq!(catalog.systems[1].errors[1].description, "<Long> explanation.\n    Second line.");
+    }
+
+    #[cfg(not(feature = "pvlost"))]
+    #[test]
+    fn test_markdown() {
+        let markdown = space().catalog().to_markdown();
+        assert!(markdown.starts_with("## (global)\n\n| Code | Message | Status Code | Description |\n|---|---|---|---|\n| 0 | Successful. | 200 | "));
+        assert!(markdown.contains("## doc\n\n"));
+        assert!(markdown.contains("| 9 | Plain. | 500 |  |\n| 10 | A \\| B. | 400 | <Long> explanation.<br>    Second line. |\n"));
+    }
+
+    #[test]
+    fn test_html() {
+        let html = space().catalog().to_html();
+        assert!(html.starts_with("<h2>(global)</h2>\n<table>\n"));
+        assert!(html.contains("<td>&lt;Long&gt; explanation.<br>    Second line.</td></tr>"));
+    }
+
+    #[cfg(all(feature = "serde", not(feature = "pvlost")))]
+    #[test]
+    fn test_json() {
+        let value: serde_json::Value = serde_json::from_str(&space().catalog().to_json().unwrap()).unwrap();
+        assert_eq!(value["systems"][1]["system"], "doc");
+        assert_eq!(value["systems"][1]["errors"][0], serde_json::json!({
+            "system": "doc",
+            "code": "9",
+            "message": "Plain.",
+            "status_code": 500,
+        }));
+        assert_eq!(value["systems"][1]["errors"][1]["description"], "<Long> explanation.\n    Second line.");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml() {
+        let yaml = space().catalog().to_yaml().unwrap();
+        assert!(yaml.contains("system: doc"));
+        assert!(yaml.contains("message: Plain."));
+    }
+}
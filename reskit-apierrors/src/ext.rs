@@ -0,0 +1,172 @@
+//! Extension traits wrapping errors into APIError with one method call.
+//!
+//! The methods build the same `APIError` as `adapt!`/`force!`, the caller is the location of the
+//! call, e.g. `src/user.rs:42:10`, instead of the function path recorded by the macros:
+//!
+//! ```ignore
+//! let user = db.find(id).adapt(&Builtin::Internal)?.ok_or_api(&Builtin::NotFound)?;
+//! ```
+#[cfg(feature = "verbose")]
+use std::collections::HashMap;
+use std::panic::Location;
+#[cfg(feature = "verbose")]
+use std::sync::{Mutex, PoisonError};
+
+use crate::globals::{adapt_errorspace, force_errorspace};
+use crate::scope::current_errorspace;
+use crate::{APIError, APIErrorMeta};
+
+#[cfg(feature = "verbose")]
+lazy_static::lazy_static! {
+    static ref CALLERS: Mutex<HashMap<&'static Location<'static>, &'static str>> = Mutex::new(HashMap::new());
+}
+
+/// ResultExt wraps the error of a Result into an APIError
+pub trait ResultExt<T> {
+    /// adapt adapts the error in the current errorspace, see `adapt!`
+    fn adapt(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+
+    /// adapt_errorspace adapts the error in the named errorspace, see `adapt_errorspace!`
+    fn adapt_errorspace(self, name: impl AsRef<str>, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+
+    /// force wraps the error with meta in the current errorspace, see `force!`
+    fn force(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+
+    /// force_errorspace wraps the error with meta in the named errorspace, see `force_errorspace!`
+    fn force_errorspace(self, name: impl AsRef<str>, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+
+    /// or_api_error keeps an APIError as is and wraps any other error with meta in the current errorspace
+    fn or_api_error(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E> where E: Into<anyhow::Error> {
+    #[track_caller]
+    fn adapt(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| adapt_errorspace(current_errorspace(), err.into(), meta, &[], caller(location)))
+    }
+
+    #[track_caller]
+    fn adapt_errorspace(self, name: impl AsRef<str>, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| adapt_errorspace(name, err.into(), meta, &[], caller(location)))
+    }
+
+    #[track_caller]
+    fn force(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| force_errorspace(current_errorspace(), err.into(), meta, &[], caller(location)))
+    }
+
+    #[track_caller]
+    fn force_errorspace(self, name: impl AsRef<str>, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| force_errorspace(name, err.into(), meta, &[], caller(location)))
+    }
+
+    #[track_caller]
+    fn or_api_error(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| {
+            let err = err.into();
+            if err.downcast_ref::<APIError>().is_some() {
+                err
+            } else {
+                force_errorspace(current_errorspace(), err, meta, &[], caller(location))
+            }
+        })
+    }
+}
+
+/// OptionExt turns a None into an APIError, typically `Builtin::NotFound`
+pub trait OptionExt<T> {
+    /// ok_or_api wraps None with meta in the current errorspace
+    fn ok_or_api(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+
+    /// ok_or_api_errorspace wraps None with meta in the named errorspace
+    fn ok_or_api_errorspace(self, name: impl AsRef<str>, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn ok_or_api(self, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.ok_or_else(|| force_errorspace(current_errorspace(), anyhow::anyhow!("none"), meta, &[], caller(location)))
+    }
+
+    #[track_caller]
+    fn ok_or_api_errorspace(self, name: impl AsRef<str>, meta: &'static dyn APIErrorMeta) -> anyhow::Result<T> {
+        let location = Location::caller();
+        self.ok_or_else(|| force_errorspace(name, anyhow::anyhow!("none"), meta, &[], caller(location)))
+    }
+}
+
+/// caller returns the location of the tracked call as `file:line:column`, interned once per call site
+/// 
+/// The location is captured eagerly, which is free, and only interned once the call fails.
+#[cfg_attr(not(feature = "verbose"), allow(unused_variables))]
+fn caller(location: &'static Location<'static>) -> Option<&'static str> {
+    #[cfg(feature = "verbose")]
+    {
+        let mut callers = CALLERS.lock().unwrap_or_else(PoisonError::into_inner);
+        let caller = callers
+            .entry(location)
+            .or_insert_with(|| Box::leak(location.to_string().into_boxed_str()));
+        Some(*caller)
+    }
+
+    #[cfg(not(feature = "verbose"))]
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use reskit_utils::init_once;
+    use crate::{APIError, APIErrorMeta, Builtin, force};
+    use super::{OptionExt, ResultExt};
+
+    fn io_error() -> std::io::Result<()> {
+        Err(std::io::Error::other("disk full"))
+    }
+
+    #[test]
+    fn test_result() {
+        init_once();
+        let err = io_error().adapt(&Builtin::Internal).unwrap_err();
+        let api_err = err.downcast_ref::<APIError>().unwrap();
+        assert_eq!(api_err.code(), "2");
+        assert_eq!(format!("{:#}", api_err.error), "disk full");
+        #[cfg(feature = "verbose")]
+        assert!(api_err.caller.unwrap().starts_with("reskit-apierrors/src/ext.rs:"));
+
+        let inner: anyhow::Result<()> = Err(force!(anyhow::anyhow!("user 42"), &Builtin::NotFound));
+        assert_eq!(inner.adapt(&Builtin::Internal).unwrap_err().downcast_ref::<APIError>().unwrap().code(), "7");
+        let inner: anyhow::Result<()> = Err(force!(anyhow::anyhow!("user 42"), &Builtin::NotFound));
+        assert_eq!(inner.force(&Builtin::Internal).unwrap_err().downcast_ref::<APIError>().unwrap().code(), "2");
+        let inner: anyhow::Result<()> = Err(force!(anyhow::anyhow!("user 42"), &Builtin::NotFound));
+        let err = inner.or_api_error(&Builtin::Internal).unwrap_err();
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().code(), "7");
+        assert!(err.downcast_ref::<APIError>().unwrap().error.downcast_ref::<APIError>().is_none());
+        assert_eq!(io_error().or_api_error(&Builtin::Internal).unwrap_err().downcast_ref::<APIError>().unwrap().code(), "2");
+        assert_eq!(io_error().force_errorspace("", &Builtin::Unavailable).unwrap_err().downcast_ref::<APIError>().unwrap().code(), "26");
+        assert!(Ok::<_, std::io::Error>(1).adapt(&Builtin::Internal).is_ok());
+    }
+
+    #[test]
+    fn test_option() {
+        init_once();
+        assert_eq!(Some(1).ok_or_api(&Builtin::NotFound).unwrap(), 1);
+        let err = None::<u32>.ok_or_api(&Builtin::NotFound).unwrap_err();
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().code(), "7");
+        let err = None::<u32>.ok_or_api_errorspace("", &Builtin::NotFound).unwrap_err();
+        assert_eq!(format!("{:#}", err.downcast_ref::<APIError>().unwrap().error), "none");
+        #[cfg(feature = "verbose")]
+        {
+            let first = err.downcast_ref::<APIError>().unwrap().caller.unwrap();
+            let again = (0..2).map(|_| None::<u32>.ok_or_api(&Builtin::NotFound).unwrap_err()).collect::<Vec<_>>();
+            let [a, b] = [&again[0], &again[1]].map(|err| err.downcast_ref::<APIError>().unwrap().caller.unwrap());
+            assert!(std::ptr::eq(a, b));
+            assert_ne!(first, a);
+        }
+    }
+}
@@ -1,16 +1,25 @@
-use std::sync::RwLock;
+use std::borrow::Cow;
+use std::sync::{RwLock, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::HashMap;
 
 use lazy_static::lazy_static;
 use linkme::distributed_slice;
 use reskit_utils::INIT_FNS;
 
-use crate::{Errorspace, APIErrorMeta, APIErrorMetas, Builtin};
+use crate::locale::{MessageBundle, fallback_chain};
+use crate::redaction::RedactionPolicy;
+use crate::{Errorspace, Registration, APIError, APIErrorMeta, APIErrorMetas, Builtin, CodeRange, RegistryError};
 
 pub const GLOBAL_ERRORSPACE_NAME: &str = "";
 
+/// ErrorspaceName is the key of ERRORSPACES, either a static str or a name built at runtime, e.g. from tenant config
+pub type ErrorspaceName = Cow<'static, str>;
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
 lazy_static! {
-    pub static ref ERRORSPACES: RwLock<HashMap<&'static str, Errorspace<'static>>> = RwLock::new(HashMap::new());
+    pub static ref ERRORSPACES: RwLock<HashMap<ErrorspaceName, Errorspace<'static>>> = RwLock::new(HashMap::new());
 }
 
 #[distributed_slice(INIT_FNS)]
@@ -19,35 +28,244 @@ pub(crate) fn init() {
     register_api_error_metas::<Builtin>();
 }
 
+/// Resolved is an api error meta found by a layered lookup
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    /// the api error meta
+    pub meta: &'static dyn APIErrorMeta,
+    /// name of the errorspace layer which answered
+    pub errorspace: ErrorspaceName,
+    /// number of parent links followed, 0 if the requested errorspace answered itself
+    pub depth: usize,
+}
+
+/// resolve look system:code up in the named errorspace then its ancestors
+fn resolve(
+    spaces: &HashMap<ErrorspaceName, Errorspace<'static>>, 
+    name: &str, 
+    system: &str, 
+    code: &str,
+) -> Result<Resolved, RegistryError> {
+    let mut current = name;
+    // a parent chain can not be longer than the registry, unless it is a cycle
+    for depth in 0..=spaces.len() {
+        let (key, space) = spaces
+            .get_key_value(current)
+            .ok_or_else(|| RegistryError::UnknownErrorspace(current.to_string()))?;
+        if let Some(meta) = space.get_api_error_meta(system, code) {
+            return Ok(Resolved { meta, errorspace: key.clone(), depth });
+        }
+        match space.parent() {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    Err(RegistryError::UnknownCode { system: system.to_string(), code: code.to_string() })
+}
+
+/// layers returns the named errorspace followed by its ancestors
+fn layers<'s>(
+    spaces: &'s HashMap<ErrorspaceName, Errorspace<'static>>, 
+    name: &str,
+) -> Result<Vec<&'s Errorspace<'static>>, RegistryError> {
+    let mut layers = Vec::new();
+    let mut current = Some(name);
+    while let Some(name) = current {
+        if layers.len() > spaces.len() {
+            break;
+        }
+        let space = spaces.get(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+        layers.push(space);
+        current = space.parent();
+    }
+    Ok(layers)
+}
+
+/// resolve_meta returns the meta adapt would wrap the anyhow::Error with
+fn resolve_meta(
+    spaces: &HashMap<ErrorspaceName, Errorspace<'static>>, 
+    name: &str, 
+    err: &anyhow::Error, 
+    default_meta: &'static dyn APIErrorMeta,
+) -> Result<&'static dyn APIErrorMeta, RegistryError> {
+    match err.downcast_ref::<APIError>() {
+        Some(ae) => resolve(spaces, name, ae.system(), ae.code()).map(|resolved| resolved.meta),
+        None if spaces.contains_key(name) => Ok(default_meta),
+        None => Err(RegistryError::UnknownErrorspace(name.to_string())),
+    }
+}
+
+/// force_in wraps the anyhow::Error with meta in the named errorspace and records it, or without one if it not exists
+fn force_in(
+    spaces: &HashMap<ErrorspaceName, Errorspace<'static>>, 
+    name: &str, 
+    err: anyhow::Error, 
+    meta: &'static dyn APIErrorMeta, 
+    mapping_names: &[&str],
+    caller: Option<&'static str>,
+) -> APIError<'static> {
+    match spaces.get_key_value(name) {
+        Some((name, space)) => APIError { errorspace: Some(name.clone()), ..space.force(err, meta, mapping_names, caller) },
+        None => APIError { meta, error: err, caller, errorspace: None },
+    }
+}
+
+/// wrap box the created APIError into an anyhow::Error, observing it unless it wraps an APIError of the same system:code,
+/// which was observed already, so rewrapping with another meta is observed as the new code
+fn wrap(api_err: APIError<'static>) -> anyhow::Error {
+    let observed = api_err.error.downcast_ref::<APIError>().is_some_and(|inner| {
+        inner.system() == api_err.system() && inner.code() == api_err.code()
+    });
+    if !observed {
+        #[cfg(feature = "tracing")]
+        crate::trace::record(&api_err);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record(crate::metrics::CREATED, &api_err, api_err.caller);
+    }
+    anyhow::Error::new(api_err)
+}
+
 /// new_errorspace create new errorspace with name and register it
-pub fn new_errorspace(name: &'static str) {
-    ERRORSPACES.write().unwrap().entry(name).or_insert(Errorspace::new());
+pub fn new_errorspace(name: impl Into<ErrorspaceName>) {
+    ERRORSPACES.write().unwrap().entry(name.into()).or_default();
 }
 
 /// register_errorspace register errorspace
-pub fn register_errorspace(name: &'static str, space: Errorspace<'static>) {
-    ERRORSPACES.write().unwrap().entry(name).or_insert(space);
-}
-
-/// clone_errorspace clone errorspace
-pub fn clone_errorspace(from: &'static str, to: &'static str) -> anyhow::Result<()>{
-    let mut spaces = ERRORSPACES.write().unwrap();
-    match spaces.get(from) {
-        None => Err(anyhow::anyhow!("errorspace {} not found", from)),
-        Some(space) => {
-            let space_clone = space.clone();
-            spaces.entry(to).or_insert(space_clone);
-            Ok(())
+pub fn register_errorspace(name: impl Into<ErrorspaceName>, space: Errorspace<'static>) {
+    ERRORSPACES.write().unwrap().entry(name.into()).or_insert(space);
+}
+
+/// try_register_errorspace register errorspace, fails if the name is already taken
+pub fn try_register_errorspace(name: impl Into<ErrorspaceName>, space: Errorspace<'static>) -> Result<(), RegistryError> {
+    let name = name.into();
+    let mut spaces = ERRORSPACES.write()?;
+    if spaces.contains_key(&name) {
+        return Err(RegistryError::Conflict(name.into_owned()));
+    }
+    spaces.insert(name, space);
+    Ok(())
+}
+
+/// try_clone_errorspace clone errorspace, if to exists then ignore, fails if from not exists
+pub fn try_clone_errorspace(from: &str, to: impl Into<ErrorspaceName>) -> Result<(), RegistryError> {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get(from).ok_or_else(|| RegistryError::UnknownErrorspace(from.to_string()))?.clone();
+    spaces.entry(to.into()).or_insert(space);
+    Ok(())
+}
+
+/// clone_errorspace clone errorspace, if to exists then ignore, see try_clone_errorspace
+pub fn clone_errorspace(from: &str, to: impl Into<ErrorspaceName>) -> anyhow::Result<()>{
+    Ok(try_clone_errorspace(from, to)?)
+}
+
+/// derive_errorspace register an empty errorspace layered on parent, fails if parent not exists or child already exists
+pub fn derive_errorspace(parent: &str, child: impl Into<ErrorspaceName>) -> Result<(), RegistryError> {
+    let child = child.into();
+    let mut spaces = ERRORSPACES.write()?;
+    if spaces.contains_key(&child) {
+        return Err(RegistryError::Conflict(child.into_owned()));
+    }
+    let (parent, _) = spaces
+        .get_key_value(parent)
+        .ok_or_else(|| RegistryError::UnknownErrorspace(parent.to_string()))?;
+    let space = Errorspace::with_parent(parent.clone());
+    spaces.insert(child, space);
+    Ok(())
+}
+
+/// effective_errorspace flatten the named errorspace and its ancestors into a standalone errorspace
+pub fn effective_errorspace(name: &str) -> Result<Errorspace<'static>, RegistryError> {
+    let spaces = ERRORSPACES.read()?;
+    let mut effective = Errorspace::new();
+    for space in layers(&spaces, name)?.into_iter().rev() {
+        for meta in space.iter() {
+            effective.overwrite_api_error_meta(meta);
+        }
+        for (locale, bundle) in space.bundles() {
+            effective.register_messages(locale, bundle.clone());
+        }
+        if space.redaction().is_some() {
+            effective.set_redaction(space.redaction());
+        }
+        for (system, range) in space.code_ranges() {
+            effective.set_code_range(system, range);
         }
     }
+    Ok(effective)
+}
+
+/// replace_errorspace register errorspace, replacing and returning the existing one with the same name
+pub fn replace_errorspace(name: impl Into<ErrorspaceName>, space: Errorspace<'static>) -> Result<Option<Errorspace<'static>>, RegistryError> {
+    Ok(ERRORSPACES.write()?.insert(name.into(), space))
+}
+
+/// remove_errorspace unregister the errorspace, returns the removed one
+/// 
+/// Removing the global errorspace is allowed, adapt! & force! then degrade to the given meta.
+pub fn remove_errorspace(name: &str) -> Result<Option<Errorspace<'static>>, RegistryError> {
+    Ok(ERRORSPACES.write()?.remove(name))
+}
+
+/// set_strict turns strict registration on or off, in strict mode registering a conflicting duplicate fails
+/// 
+/// Turn it on before `init_once` to make conflicting catalogs fail startup.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::SeqCst);
+}
+
+/// is_strict returns whether strict registration is on
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::SeqCst)
+}
+
+/// try_register_api_error_metas_errorspace register APIErrorMetas, if variant exists(system:code) then ignore
+/// 
+/// Returns the registration outcome of every meta. In strict mode nothing is registered if any meta conflicts
+/// or is out of the codes reserved by its system.
+pub fn try_register_api_error_metas_errorspace<T>(name: &str) -> Result<Vec<Registration<'static>>, RegistryError> where T: APIErrorMetas + 'static {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+    register_metas(space, T::api_error_metas(), is_strict())
+}
+
+/// register_metas register the metas into space, in strict mode nothing is registered if any meta is rejected
+fn register_metas(
+    space: &mut Errorspace<'static>, 
+    metas: Vec<&'static dyn APIErrorMeta>, 
+    strict: bool,
+) -> Result<Vec<Registration<'static>>, RegistryError> {
+    if strict {
+        for registration in space.check_api_error_metas(&metas) {
+            match registration {
+                Registration::Conflict { existing, rejected } => return Err(RegistryError::MetaConflict {
+                    system: rejected.system().to_string(),
+                    code: rejected.code().to_string(),
+                    existing: existing.to_string(),
+                    rejected: rejected.to_string(),
+                }),
+                Registration::OutOfRange { rejected, range } => return Err(RegistryError::CodeOutOfRange {
+                    system: rejected.system().to_string(),
+                    code: rejected.code().to_string(),
+                    start: range.start(),
+                    end: range.end(),
+                }),
+                _ => {},
+            }
+        }
+    }
+    Ok(metas.into_iter().map(|meta| space.register_api_error_meta(meta)).collect())
 }
 
 /// register_api_error_metas register APIErrorMetas, if variant exists(system:code) then ignore
+/// 
+/// # Panics
+/// 
+/// Panics if the errorspace not exists, the registry lock is poisoned, or a meta conflicts in strict mode,
+/// see try_register_api_error_metas_errorspace.
 pub fn register_api_error_metas_errorspace<T>(name: &str) where T: APIErrorMetas + 'static {
-    let mut spaces = ERRORSPACES.write().unwrap();
-    let space = spaces.get_mut(name).unwrap();
-    for meta in T::api_error_metas() {
-        space.register_api_error_meta(meta);
+    if let Err(err) = try_register_api_error_metas_errorspace::<T>(name) {
+        panic!("register api error metas to errorspace {:?}: {}", name, err);
     }
 }
 
@@ -56,13 +274,23 @@ pub fn register_api_error_metas<T>() where T: APIErrorMetas + 'static {
     register_api_error_metas_errorspace::<T>(GLOBAL_ERRORSPACE_NAME);
 }
 
-/// overwrite_api_error_metas overwrite existing api error meta with APIErrorMetas, used for stauts code rebinding
-pub fn overwrite_api_error_metas_errorspace<T>(name: &str) where T: APIErrorMetas + 'static {
-    let mut spaces = ERRORSPACES.write().unwrap();
-    let space = spaces.get_mut(name).unwrap();
+/// try_overwrite_api_error_metas_errorspace overwrite existing api error meta with APIErrorMetas, used for stauts code rebinding
+pub fn try_overwrite_api_error_metas_errorspace<T>(name: &str) -> Result<(), RegistryError> where T: APIErrorMetas + 'static {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
     for meta in T::api_error_metas() {
         space.overwrite_api_error_meta(meta);
     }
+    Ok(())
+}
+
+/// overwrite_api_error_metas overwrite existing api error meta with APIErrorMetas, used for stauts code rebinding
+/// 
+/// # Panics
+/// 
+/// Panics if the errorspace not exists or the registry lock is poisoned, see try_overwrite_api_error_metas_errorspace.
+pub fn overwrite_api_error_metas_errorspace<T>(name: &str) where T: APIErrorMetas + 'static {
+    try_overwrite_api_error_metas_errorspace::<T>(name).unwrap();
 }
 
 /// overwrite_api_error_metas overwrite existing api error meta with APIErrorMetas, used for stauts code rebinding
@@ -70,9 +298,123 @@ pub fn overwrite_api_error_metas<T>() where T: APIErrorMetas + 'static {
     overwrite_api_error_metas_errorspace::<T>(GLOBAL_ERRORSPACE_NAME);
 }
 
-/// get_api_error_meta get api error meta for specified systen & code
+/// unregister_api_error_metas_errorspace remove every system:code of APIErrorMetas, returns the removed metas
+/// 
+/// The removed meta is whatever is registered under system:code, which may be a rebinding of another APIErrorMetas.
+pub fn unregister_api_error_metas_errorspace<T>(name: &str) -> Result<Vec<&'static dyn APIErrorMeta>, RegistryError> where T: APIErrorMetas + 'static {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+    Ok(T::api_error_metas()
+        .into_iter()
+        .filter_map(|meta| space.unregister_api_error_meta(meta.system(), meta.code()))
+        .collect())
+}
+
+/// unregister_api_error_metas remove every system:code of APIErrorMetas from global errorspace, returns the removed metas
+pub fn unregister_api_error_metas<T>() -> Result<Vec<&'static dyn APIErrorMeta>, RegistryError> where T: APIErrorMetas + 'static {
+    unregister_api_error_metas_errorspace::<T>(GLOBAL_ERRORSPACE_NAME)
+}
+
+/// remove_system_errorspace remove all api error metas of the system, returns the removed metas
+pub fn remove_system_errorspace(name: &str, system: &str) -> Result<Vec<&'static dyn APIErrorMeta>, RegistryError> {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+    Ok(space.remove_system(system))
+}
+
+/// register_messages_errorspace add localized messages of locale to the named errorspace
+pub fn register_messages_errorspace(name: &str, locale: &str, bundle: MessageBundle) -> Result<(), RegistryError> {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+    space.register_messages(locale, bundle);
+    Ok(())
+}
+
+/// register_messages add localized messages of locale to global errorspace
+/// 
+/// # Panics
+/// 
+/// Panics if the registry lock is poisoned or global errorspace is removed.
+pub fn register_messages(locale: &str, bundle: MessageBundle) {
+    register_messages_errorspace(GLOBAL_ERRORSPACE_NAME, locale, bundle).unwrap();
+}
+
+/// localized_message_errorspace get the message of system:code for locale from the named errorspace and its parents
+/// 
+/// The most specific locale wins over the nearest layer, e.g. a zh-CN message of the parent is preferred to a zh
+/// message of the child. Returns None if no bundle has the message, callers fall back to `APIErrorMeta::message`.
+pub fn localized_message_errorspace(name: impl AsRef<str>, system: &str, code: &str, locale: &str) -> Option<String> {
+    resolve_message_errorspace(name, system, code, locale).map(|(message, _)| message)
+}
+
+/// resolve_message_errorspace is localized_message_errorspace also returning the normalized locale of the matched bundle
+pub(crate) fn resolve_message_errorspace(name: impl AsRef<str>, system: &str, code: &str, locale: &str) -> Option<(String, String)> {
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    let layers = layers(&spaces, name.as_ref()).ok()?;
+    fallback_chain(locale).into_iter().find_map(|locale| {
+        let message = layers.iter().find_map(|space| space.get_message(&locale, system, code))?;
+        Some((message.to_string(), locale))
+    })
+}
+
+/// set_redaction_errorspace set the redaction policy of the named errorspace, None to inherit the policy of the parent
+pub fn set_redaction_errorspace(name: &str, policy: Option<RedactionPolicy>) -> Result<(), RegistryError> {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+    space.set_redaction(policy);
+    Ok(())
+}
+
+/// redaction_errorspace returns the redaction policy of the named errorspace or its nearest ancestor setting one
+/// 
+/// Falls back to the default policy, which redacts everything, if no layer sets one or the errorspace not exists.
+pub fn redaction_errorspace(name: impl AsRef<str>) -> RedactionPolicy {
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    layers(&spaces, name.as_ref())
+        .ok()
+        .and_then(|layers| layers.iter().find_map(|space| space.redaction()))
+        .unwrap_or_default()
+}
+
+/// reserve_codes_errorspace reserve the numeric codes of range for system in the named errorspace, see `Errorspace::reserve_codes`
+pub fn reserve_codes_errorspace(name: &str, system: &str, range: CodeRange) -> Result<(), RegistryError> {
+    let mut spaces = ERRORSPACES.write()?;
+    let space = spaces.get_mut(name).ok_or_else(|| RegistryError::UnknownErrorspace(name.to_string()))?;
+    space.reserve_codes(system, range)
+}
+
+/// code_range_errorspace returns the codes reserved by system in the named errorspace or its nearest ancestor reserving them
+pub fn code_range_errorspace(name: impl AsRef<str>, system: &str) -> Option<CodeRange> {
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    layers(&spaces, name.as_ref()).ok()?.iter().find_map(|space| space.code_range(system))
+}
+
+/// global_code_errorspace returns the global code of meta by the codes its system reserves in the named errorspace and its parents
+pub fn global_code_errorspace(name: impl AsRef<str>, meta: &dyn APIErrorMeta) -> Option<u64> {
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    layers(&spaces, name.as_ref()).ok()?.iter().find_map(|space| space.global_code(meta))
+}
+
+/// get_by_global_code_errorspace get the api error meta of the global code from the named errorspace and its parents
+pub fn get_by_global_code_errorspace(name: impl AsRef<str>, global_code: u64) -> Option<&'static dyn APIErrorMeta> {
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    layers(&spaces, name.as_ref()).ok()?.iter().find_map(|space| space.get_by_global_code(global_code))
+}
+
+/// resolve_api_error_meta_errorspace get api error meta for specified systen & code, and report which layer answered
+pub fn resolve_api_error_meta_errorspace(name: &str, system: &str, code: &str) -> Result<Resolved, RegistryError> {
+    let spaces = ERRORSPACES.read()?;
+    resolve(&spaces, name, system, code)
+}
+
+/// try_get_api_error_meta_errorspace get api error meta for specified systen & code, falling back to the parents
+pub fn try_get_api_error_meta_errorspace(name: &str, system: &str, code: &str) -> Result<&'static dyn APIErrorMeta, RegistryError> {
+    resolve_api_error_meta_errorspace(name, system, code).map(|resolved| resolved.meta)
+}
+
+/// get_api_error_meta get api error meta for specified systen & code, None if the errorspace or meta not exists
 pub fn get_api_error_meta_errorspace(name: &str, system: &str, code: &str) -> Option<&'static dyn APIErrorMeta> {
-    ERRORSPACES.read().unwrap().get(name).unwrap().get_api_error_meta(system, code)
+    try_get_api_error_meta_errorspace(name, system, code).ok()
 }
 
 /// get_api_error_meta get api error meta for specified systen & code
@@ -80,41 +422,100 @@ pub fn get_api_error_meta(system: &str, code: &str) -> Option<&'static dyn APIEr
     get_api_error_meta_errorspace(GLOBAL_ERRORSPACE_NAME, system, code)
 }
 
-/// adapt_errorspace adapts anyhow::Error to specify error space, or wrap it with default_meta as a APIError in global error space
-#[allow(dead_code)]
-pub(crate) fn adapt_errorspace(
-    name: &str, 
+/// try_adapt_errorspace adapts anyhow::Error to specify error space, or wrap it with default_meta as a APIError
+/// 
+/// Fails if the errorspace not exists or the APIError is unknown to it, the anyhow::Error is handed back on failure.
+pub fn try_adapt_errorspace(
+    name: impl AsRef<str>, 
+    err: anyhow::Error, 
+    default_meta: &'static dyn APIErrorMeta, 
+    mapping_names: &[&str],
+    caller: Option<&'static str>,
+) -> Result<anyhow::Error, (RegistryError, anyhow::Error)> {
+    let name = name.as_ref();
+    let spaces = match ERRORSPACES.read() {
+        Ok(spaces) => spaces,
+        Err(poisoned) => return Err((poisoned.into(), err)),
+    };
+    match resolve_meta(&spaces, name, &err, default_meta) {
+        Ok(meta) => Ok(wrap(force_in(&spaces, name, err, meta, mapping_names, caller))),
+        Err(registry_err) => Err((registry_err, err)),
+    }
+}
+
+/// adapt_errorspace adapts anyhow::Error to specify error space, or wrap it with default_meta as a APIError
+/// 
+/// Never panics: a missing errorspace, an unknown APIError or a poisoned registry degrade to default_meta.
+#[doc(hidden)]
+pub fn adapt_errorspace(
+    name: impl AsRef<str>, 
     err: anyhow::Error, 
     default_meta: &'static dyn APIErrorMeta, 
     mapping_names: &[&str],
     caller: Option<&'static str>,
 ) -> anyhow::Error {
-    let spaces = ERRORSPACES.read().unwrap();
-    let space = spaces.get(name).unwrap();
-    let api_err = space.adapt(err, default_meta, mapping_names, caller);
-    anyhow::Error::new(api_err)
+    let name = name.as_ref();
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    let meta = resolve_meta(&spaces, name, &err, default_meta).unwrap_or(default_meta);
+    wrap(force_in(&spaces, name, err, meta, mapping_names, caller))
 }
 
-/// force wraps the anyhow::Error with given meta as a APIError in global error space
-#[allow(dead_code)]
-pub(crate) fn force_errorspace(
-    name: &str, 
+/// try_force_errorspace wraps the anyhow::Error with given meta as a APIError
+/// 
+/// Fails if the errorspace not exists, the anyhow::Error is handed back on failure.
+pub fn try_force_errorspace(
+    name: impl AsRef<str>, 
+    err: anyhow::Error, 
+    meta: &'static dyn APIErrorMeta, 
+    mapping_names: &[&str],
+    caller: Option<&'static str>,
+) -> Result<anyhow::Error, (RegistryError, anyhow::Error)> {
+    let name = name.as_ref();
+    let spaces = match ERRORSPACES.read() {
+        Ok(spaces) => spaces,
+        Err(poisoned) => return Err((poisoned.into(), err)),
+    };
+    if !spaces.contains_key(name) {
+        return Err((RegistryError::UnknownErrorspace(name.to_string()), err));
+    }
+    Ok(wrap(force_in(&spaces, name, err, meta, mapping_names, caller)))
+}
+
+/// force_errorspace wraps the anyhow::Error with given meta as a APIError
+/// 
+/// Never panics: a missing errorspace or a poisoned registry still wrap with meta.
+#[doc(hidden)]
+pub fn force_errorspace(
+    name: impl AsRef<str>, 
     err: anyhow::Error, 
     meta: &'static dyn APIErrorMeta, 
     mapping_names: &[&str],
     caller: Option<&'static str>,
 ) -> anyhow::Error {
-    let spaces = ERRORSPACES.read().unwrap();
-    let space = spaces.get(name).unwrap();
-    let api_err = space.force(err, meta, mapping_names, caller);
-    anyhow::Error::new(api_err)
+    let spaces = ERRORSPACES.read().unwrap_or_else(PoisonError::into_inner);
+    wrap(force_in(&spaces, name.as_ref(), err, meta, mapping_names, caller))
 }
 
 #[cfg(test)]
 mod tests {
     use http_types::StatusCode;
     use reskit_utils::init_once;
-    use super::{get_api_error_meta, get_api_error_meta_errorspace, clone_errorspace};
+    use crate::{Builtin, RegistryError, APIError, APIErrorMeta, APIErrorMetas, AsAPIErrorMeta};
+    use super::{
+        get_api_error_meta, get_api_error_meta_errorspace, clone_errorspace, try_clone_errorspace,
+        try_get_api_error_meta_errorspace, try_register_api_error_metas_errorspace,
+        try_adapt_errorspace, try_force_errorspace, adapt_errorspace, force_errorspace,
+        new_errorspace, register_api_error_metas_errorspace,
+        replace_errorspace, remove_errorspace, unregister_api_error_metas_errorspace,
+        remove_system_errorspace, derive_errorspace, effective_errorspace,
+        resolve_api_error_meta_errorspace, overwrite_api_error_metas_errorspace,
+        register_metas, is_strict, register_messages_errorspace, localized_message_errorspace,
+        set_redaction_errorspace, redaction_errorspace, reserve_codes_errorspace,
+        code_range_errorspace, global_code_errorspace, get_by_global_code_errorspace,
+    };
+    use crate::locale::MessageBundle;
+    use crate::redaction::RedactionPolicy;
+    use crate::Errorspace;
         
     #[test]
     fn test_init() {
@@ -138,4 +539,227 @@ mod tests {
         assert_eq!(err.system(), "");
         assert_eq!(err.message(), "Internal server error.");
     }
+
+    #[test]
+    fn test_try_errorspace() {
+        init_once();
+        assert!(clone_errorspace("not_exists", "not_exists_clone").is_err());
+        assert!(matches!(try_clone_errorspace("not_exists", "not_exists_clone"), Err(RegistryError::UnknownErrorspace(_))));
+        assert!(get_api_error_meta_errorspace("not_exists_clone", "", "1").is_none());
+        clone_errorspace("", "").unwrap();
+        assert!(matches!(try_register_api_error_metas_errorspace::<Builtin>("not_exists"), Err(RegistryError::UnknownErrorspace(_))));
+        assert!(get_api_error_meta_errorspace("not_exists", "", "1").is_none());
+        assert!(matches!(try_get_api_error_meta_errorspace("not_exists", "", "1"), Err(RegistryError::UnknownErrorspace(_))));
+        assert_eq!(
+            try_get_api_error_meta_errorspace("", "not_exists", "1").unwrap_err(),
+            RegistryError::UnknownCode { system: "not_exists".to_string(), code: "1".to_string() },
+        );
+        assert_eq!(try_get_api_error_meta_errorspace("", "", "1").unwrap().message(), "Unknown error.");
+    }
+
+    #[test]
+    fn test_adapt_degrade() {
+        init_once();
+        let err = adapt_errorspace("not_exists", anyhow::anyhow!("xxx"), &Builtin::Internal, &[], None);
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().code(), "2");
+        assert_eq!(format!("{:#}", err.downcast_ref::<APIError>().unwrap().error), "xxx");
+        let err = force_errorspace("not_exists", anyhow::anyhow!("xxx"), &Builtin::Internal, &[], None);
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().code(), "2");
+        assert_eq!(format!("{:#}", err.downcast_ref::<APIError>().unwrap().error), "xxx");
+
+        #[derive(Debug, AsAPIErrorMeta)]
+        enum Unregistered {
+            #[apierrormeta(system="unregistered", code="1", message="Unregistered.", status_code=400)]
+            Unregistered,
+        }
+        let unknown = || anyhow::Error::new(APIError { meta: &Unregistered::Unregistered, error: anyhow::anyhow!("xxx"), caller: None, errorspace: None });
+        let err = adapt_errorspace("", unknown(), &Builtin::Unknown, &[], None);
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().code(), "1");
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().system(), "");
+        let (registry_err, err) = try_adapt_errorspace("", unknown(), &Builtin::Unknown, &[], None).unwrap_err();
+        assert_eq!(registry_err, RegistryError::UnknownCode { system: "unregistered".to_string(), code: "1".to_string() });
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().system(), "unregistered");
+        let (registry_err, err) = try_adapt_errorspace("not_exists", anyhow::anyhow!("xxx"), &Builtin::Unknown, &[], None).unwrap_err();
+        assert!(matches!(registry_err, RegistryError::UnknownErrorspace(_)));
+        assert_eq!(format!("{}", err), "xxx");
+        let (registry_err, err) = try_force_errorspace("not_exists", anyhow::anyhow!("xxx"), &Builtin::Unknown, &[], None).unwrap_err();
+        assert!(matches!(registry_err, RegistryError::UnknownErrorspace(_)));
+        assert_eq!(format!("{}", err), "xxx");
+    }
+
+    #[test]
+    fn test_runtime_name() {
+        init_once();
+        let tenant = format!("tenant-{}", 42);
+        new_errorspace(tenant.clone());
+        register_api_error_metas_errorspace::<Builtin>(&tenant);
+        assert_eq!(get_api_error_meta_errorspace(&tenant, "", "2").unwrap().message(), "Internal server error.");
+        clone_errorspace(&tenant, format!("{}-clone", tenant)).unwrap();
+        assert_eq!(get_api_error_meta_errorspace("tenant-42-clone", "", "2").unwrap().code(), "2");
+        let err = adapt_errorspace(&tenant, anyhow::anyhow!("xxx"), &Builtin::Unknown, &[], None);
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().code(), "1");
+        assert_eq!(format!("{:#}", err.downcast_ref::<APIError>().unwrap().error), "xxx");
+    }
+
+    #[test]
+    fn test_remove() {
+        init_once();
+        new_errorspace("remove");
+        register_api_error_metas_errorspace::<Builtin>("remove");
+        let removed = unregister_api_error_metas_errorspace::<Builtin>("remove").unwrap();
+        assert_eq!(removed.len(), Builtin::api_error_metas().len());
+        assert!(unregister_api_error_metas_errorspace::<Builtin>("remove").unwrap().is_empty());
+        assert!(get_api_error_meta_errorspace("remove", "", "1").is_none());
+        register_api_error_metas_errorspace::<Builtin>("remove");
+        assert_eq!(remove_system_errorspace("remove", "").unwrap().len(), Builtin::api_error_metas().len());
+        assert!(matches!(remove_system_errorspace("not_exists", ""), Err(RegistryError::UnknownErrorspace(_))));
+
+        let replaced = replace_errorspace("remove", Errorspace::new()).unwrap();
+        assert!(replaced.is_some());
+        assert!(replace_errorspace("replace", Errorspace::new()).unwrap().is_none());
+        assert!(remove_errorspace("remove").unwrap().is_some());
+        assert!(remove_errorspace("remove").unwrap().is_none());
+        assert!(remove_errorspace("replace").unwrap().is_some());
+        assert!(get_api_error_meta_errorspace("remove", "", "1").is_none());
+    }
+
+    #[test]
+    fn test_layered() {
+        #[derive(Debug, AsAPIErrorMeta)]
+        enum Rebinds {
+            #[apierrormeta(system="", code="1", message="Unknown error.", status_code=200)]
+            Unknown,
+        }
+
+        init_once();
+        derive_errorspace("", "partner").unwrap();
+        derive_errorspace("partner", "partner-child").unwrap();
+        assert!(matches!(derive_errorspace("", "partner"), Err(RegistryError::Conflict(_))));
+        assert!(matches!(derive_errorspace("not_exists", "orphan"), Err(RegistryError::UnknownErrorspace(_))));
+        overwrite_api_error_metas_errorspace::<Rebinds>("partner");
+
+        let resolved = resolve_api_error_meta_errorspace("partner-child", "", "1").unwrap();
+        assert_eq!(resolved.meta.status_code(), StatusCode::Ok);
+        assert_eq!(resolved.errorspace, "partner");
+        assert_eq!(resolved.depth, 1);
+        let resolved = resolve_api_error_meta_errorspace("partner-child", "", "2").unwrap();
+        assert_eq!(resolved.meta.status_code(), StatusCode::InternalServerError);
+        assert_eq!(resolved.errorspace, "");
+        assert_eq!(resolved.depth, 2);
+        assert_eq!(get_api_error_meta("", "1").unwrap().status_code(), StatusCode::InternalServerError);
+        assert!(matches!(resolve_api_error_meta_errorspace("partner-child", "", "999"), Err(RegistryError::UnknownCode { .. })));
+
+        let inner = || force_errorspace("", anyhow::anyhow!("xxx"), &Builtin::Unknown, &[], None);
+        let err = adapt_errorspace("partner-child", inner(), &Builtin::Internal, &[], None);
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().status_code(), StatusCode::Ok);
+        let err = try_adapt_errorspace("partner-child", inner(), &Builtin::Internal, &[], None).unwrap();
+        assert_eq!(err.downcast_ref::<APIError>().unwrap().status_code(), StatusCode::Ok);
+
+        let effective = effective_errorspace("partner-child").unwrap();
+        assert!(effective.parent().is_none());
+        assert_eq!(effective.get_api_error_meta("", "1").unwrap().status_code(), StatusCode::Ok);
+        assert_eq!(effective.len(""), Builtin::api_error_metas().len());
+    }
+
+    #[test]
+    fn test_strict() {
+        #[derive(Debug, AsAPIErrorMeta)]
+        enum Conflicting {
+            #[apierrormeta(system="", code="2", message="Conflicting.", status_code=400)]
+            Internal,
+        }
+        #[derive(Debug, AsAPIErrorMeta)]
+        enum SelfConflicting {
+            #[apierrormeta(system="self_conflicting", code="1", message="First.", status_code=400)]
+            First,
+            #[apierrormeta(system="self_conflicting", code="1", message="Second.", status_code=400)]
+            Second,
+        }
+
+        init_once();
+        new_errorspace("strict");
+        register_api_error_metas_errorspace::<Builtin>("strict");
+        let registrations = try_register_api_error_metas_errorspace::<SelfConflicting>("strict").unwrap();
+        assert!(matches!(registrations[0], crate::Registration::Inserted));
+        assert!(registrations[1].is_conflict());
+        assert!(!is_strict());
+
+        // strict mode is process wide, test it on a local errorspace instead of toggling it
+        let mut space = Errorspace::new();
+        for meta in Builtin::api_error_metas() {
+            space.register_api_error_meta(meta);
+        }
+        let registrations = register_metas(&mut space, vec![&Builtin::Unknown, &Conflicting::Internal], false).unwrap();
+        assert!(matches!(registrations[0], crate::Registration::Identical));
+        assert!(registrations[1].is_conflict());
+        match register_metas(&mut space, vec![&Builtin::Unknown, &Conflicting::Internal], true) {
+            Err(RegistryError::MetaConflict { system, code, existing, rejected }) => {
+                assert_eq!(format!("{}:{}", system, code), ":2");
+                assert_eq!(existing, Builtin::Internal.to_string());
+                assert_eq!(rejected, Conflicting::Internal.to_string());
+            },
+            _ => panic!("expected a conflict"),
+        }
+        assert_eq!(space.get_api_error_meta("", "2").unwrap().message(), "Internal server error.");
+
+        match register_metas(&mut space, SelfConflicting::api_error_metas(), true) {
+            Err(RegistryError::MetaConflict { existing, rejected, .. }) => {
+                assert_eq!(existing, SelfConflicting::First.to_string());
+                assert_eq!(rejected, SelfConflicting::Second.to_string());
+            },
+            _ => panic!("expected a conflict within the batch"),
+        }
+        assert!(space.get_api_error_meta("self_conflicting", "1").is_none());
+    }
+
+    #[test]
+    fn test_localized_messages() {
+        init_once();
+        derive_errorspace("", "localized").unwrap();
+        register_messages_errorspace("", "zh-CN", MessageBundle::new().with("", "1", "未知错误。")).unwrap();
+        register_messages_errorspace("localized", "zh", MessageBundle::new().with("", "1", "未知的错误。").with("", "2", "内部错误。")).unwrap();
+        assert_eq!(localized_message_errorspace("localized", "", "1", "zh-CN").unwrap(), "未知错误。");
+        assert_eq!(localized_message_errorspace("localized", "", "1", "zh-TW").unwrap(), "未知的错误。");
+        assert_eq!(localized_message_errorspace("localized", "", "2", "zh-CN").unwrap(), "内部错误。");
+        assert!(localized_message_errorspace("localized", "", "2", "en").is_none());
+        assert!(localized_message_errorspace("not_exists", "", "1", "zh").is_none());
+        assert!(matches!(register_messages_errorspace("not_exists", "zh", MessageBundle::new()), Err(RegistryError::UnknownErrorspace(_))));
+
+        let effective = effective_errorspace("localized").unwrap();
+        assert_eq!(effective.localized_message("", "1", "zh-CN"), Some("未知错误。"));
+        assert_eq!(effective.localized_message("", "2", "zh-CN"), Some("内部错误。"));
+    }
+
+    #[test]
+    fn test_redaction() {
+        init_once();
+        new_errorspace("redaction");
+        derive_errorspace("redaction", "redaction_child").unwrap();
+        assert_eq!(redaction_errorspace("redaction_child"), RedactionPolicy::RedactAll);
+        set_redaction_errorspace("redaction", Some(RedactionPolicy::RedactServerErrors)).unwrap();
+        assert_eq!(redaction_errorspace("redaction_child"), RedactionPolicy::RedactServerErrors);
+        set_redaction_errorspace("redaction_child", Some(RedactionPolicy::ExposeAll)).unwrap();
+        assert_eq!(redaction_errorspace("redaction_child"), RedactionPolicy::ExposeAll);
+        assert_eq!(effective_errorspace("redaction_child").unwrap().redaction(), Some(RedactionPolicy::ExposeAll));
+        set_redaction_errorspace("redaction_child", None).unwrap();
+        assert_eq!(redaction_errorspace("redaction_child"), RedactionPolicy::RedactServerErrors);
+        assert_eq!(redaction_errorspace("not_exists"), RedactionPolicy::RedactAll);
+        assert!(matches!(set_redaction_errorspace("not_exists", None), Err(RegistryError::UnknownErrorspace(_))));
+    }
+
+    #[test]
+    fn test_code_range() {
+        init_once();
+        new_errorspace("ranged");
+        derive_errorspace("ranged", "ranged_child").unwrap();
+        reserve_codes_errorspace("ranged", "", crate::CodeRange::new(0, 0..=99)).unwrap();
+        assert_eq!(code_range_errorspace("ranged_child", "").unwrap().end(), 99);
+        assert_eq!(code_range_errorspace("ranged_child", "other"), None);
+        register_api_error_metas_errorspace::<Builtin>("ranged");
+        assert_eq!(global_code_errorspace("ranged_child", &Builtin::Unavailable), Some(26));
+        assert_eq!(get_by_global_code_errorspace("ranged_child", 26).unwrap().code(), "26");
+        assert_eq!(effective_errorspace("ranged_child").unwrap().code_range(""), code_range_errorspace("ranged", ""));
+        assert_eq!(global_code_errorspace("", &Builtin::Unavailable), None);
+        assert!(matches!(reserve_codes_errorspace("not_exists", "", crate::CodeRange::new(0, 0..=99)), Err(RegistryError::UnknownErrorspace(_))));
+    }
 }
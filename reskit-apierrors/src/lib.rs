@@ -1,45 +1,121 @@
 #[macro_use]
 extern crate shadow_rs;
 
-shadow!(build);
+// the generated shadow.rs has blank lines after its doc comments
+#[allow(clippy::empty_line_after_doc_comments)]
+mod shadow {
+    shadow!(build);
+}
+
+pub use shadow::build;
+
+#[cfg(feature = "log")]
+extern crate log_crate as log;
+
+#[cfg(feature = "tracing")]
+extern crate tracing_crate as tracing;
 
 pub mod status_code;
+pub mod error;
 pub mod apierror;
 pub mod globals;
 pub mod errorspace;
+pub mod scope;
+pub mod validate;
+pub mod export;
+pub mod locale;
+pub mod redaction;
+pub mod retry;
+pub mod severity;
 pub mod builtin;
 pub mod macros;
+pub mod ext;
 pub mod prelude;
 
-#[cfg(feature = "pvlost")]
+#[cfg(feature = "serde")]
+pub mod response;
+
 pub mod pvlost;
 
-#[allow(unused_qualifications)]
+#[cfg(feature = "pvlost")]
+pub mod availability;
+
+#[cfg(feature = "tracing")]
+pub mod trace;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
 pub use apierror::{APIErrorMeta, APIErrorMetas, APIError};
-pub use errorspace::Errorspace;
+pub use error::RegistryError;
+pub use locale::MessageBundle;
+pub use redaction::RedactionPolicy;
+pub use retry::Retryable;
+pub use severity::Severity;
+pub use ext::{OptionExt, ResultExt};
+pub use errorspace::{Errorspace, Registration, Summary, CodeRange, GLOBAL_CODE_BASE};
 pub use builtin::Builtin;
+pub use scope::{current_errorspace, enter_errorspace, with_errorspace, ErrorspaceFutureExt};
 pub use globals::{
     GLOBAL_ERRORSPACE_NAME,
+    ErrorspaceName,
+    Resolved,
     ERRORSPACES,
     new_errorspace,
     register_errorspace,
+    try_register_errorspace,
     clone_errorspace,
+    try_clone_errorspace,
+    derive_errorspace,
+    effective_errorspace,
+    replace_errorspace,
+    remove_errorspace,
     register_api_error_metas,
     overwrite_api_error_metas,
     get_api_error_meta,
     register_api_error_metas_errorspace,
     overwrite_api_error_metas_errorspace,
     get_api_error_meta_errorspace,
+    try_register_api_error_metas_errorspace,
+    try_overwrite_api_error_metas_errorspace,
+    try_get_api_error_meta_errorspace,
+    resolve_api_error_meta_errorspace,
+    try_adapt_errorspace,
+    try_force_errorspace,
+    set_strict,
+    is_strict,
+    unregister_api_error_metas,
+    unregister_api_error_metas_errorspace,
+    remove_system_errorspace,
+    register_messages,
+    register_messages_errorspace,
+    localized_message_errorspace,
+    set_redaction_errorspace,
+    redaction_errorspace,
+    reserve_codes_errorspace,
+    code_range_errorspace,
+    global_code_errorspace,
+    get_by_global_code_errorspace,
 };
 
-#[cfg(feature = "pvlost")]
-pub use pvlost::PVLost;
+pub use pvlost::{PVLost, PVLostHeader, Reason};
+pub use status_code::Status;
+
+#[cfg(feature = "log")]
+pub use severity::log_error;
 
 pub use reskit_apierrors_derive::*;
 
+// used by bail_api!, so callers do not depend on anyhow and reskit-utils themselves
+#[doc(hidden)]
+pub use anyhow as __anyhow;
+#[doc(hidden)]
+pub use reskit_utils::caller as __caller;
+
 #[cfg(test)]
 mod tests {
     #[test]
+    #[allow(clippy::empty_line_after_doc_comments)]
     fn test_version() {
         shadow!(build);
         assert_eq!(build::PKG_VERSION, "0.1.0"); 
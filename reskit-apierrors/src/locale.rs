@@ -0,0 +1,124 @@
+//! Localized error messages.
+//!
+//! A `MessageBundle` holds the messages of one locale keyed by system:code, bundles are registered
+//! on an errorspace. Lookups try the most specific locale first, e.g. `zh-Hant-TW`, `zh-Hant`, `zh`,
+//! and fall back to `APIErrorMeta::message`.
+use std::collections::HashMap;
+
+/// MessageBundle maps system:code to the message of one locale
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageBundle {
+    messages: HashMap<String, HashMap<String, String>>,
+}
+
+impl MessageBundle {
+    pub fn new() -> MessageBundle {
+        MessageBundle::default()
+    }
+
+    /// with add a message, for building bundles inline
+    pub fn with(mut self, system: &str, code: &str, message: impl Into<String>) -> MessageBundle {
+        self.insert(system, code, message);
+        self
+    }
+
+    /// insert add a message, returns the replaced one
+    pub fn insert(&mut self, system: &str, code: &str, message: impl Into<String>) -> Option<String> {
+        self.messages.entry(system.to_string()).or_default().insert(code.to_string(), message.into())
+    }
+
+    pub fn get(&self, system: &str, code: &str) -> Option<&str> {
+        self.messages.get(system)?.get(code).map(String::as_str)
+    }
+
+    /// extend add all messages of other, overwriting existing ones
+    pub fn extend(&mut self, other: MessageBundle) {
+        for (system, messages) in other.messages {
+            self.messages.entry(system).or_default().extend(messages);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// normalize_locale lowercase the locale and use `-` as separator, so `zh_CN` matches `zh-cn`
+pub fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// fallback_chain returns the normalized locale followed by its less specific parents
+pub fn fallback_chain(locale: &str) -> Vec<String> {
+    let mut locale = normalize_locale(locale);
+    let mut chain = Vec::new();
+    while !locale.is_empty() {
+        chain.push(locale.clone());
+        match locale.rfind('-') {
+            Some(pos) => locale.truncate(pos),
+            None => break,
+        }
+    }
+    chain
+}
+
+/// parse_accept_language returns the languages of an `Accept-Language` header by descending quality
+///
+/// The wildcard `*` and languages with `q=0` are dropped, ties keep the header order.
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut languages: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let language = parts.next()?.trim();
+            if language.is_empty() || language == "*" {
+                return None;
+            }
+            let mut quality = 1.0;
+            for param in parts {
+                if let Some(q) = param.trim().strip_prefix("q=") {
+                    quality = q.trim().parse().unwrap_or(0.0);
+                }
+            }
+            if quality <= 0.0 {
+                return None;
+            }
+            Some((language.to_string(), quality))
+        })
+        .collect();
+    languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    languages.into_iter().map(|(language, _)| language).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageBundle, fallback_chain, parse_accept_language};
+
+    #[test]
+    fn test_bundle() {
+        let mut bundle = MessageBundle::new().with("", "1", "未知错误。");
+        assert_eq!(bundle.get("", "1"), Some("未知错误。"));
+        assert_eq!(bundle.get("", "2"), None);
+        assert_eq!(bundle.insert("", "1", "未知的错误。"), Some("未知错误。".to_string()));
+        bundle.extend(MessageBundle::new().with("", "2", "服务内部错误。"));
+        assert_eq!(bundle.len(), 2);
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        assert_eq!(fallback_chain("zh_Hant-TW"), vec!["zh-hant-tw", "zh-hant", "zh"]);
+        assert_eq!(fallback_chain("en"), vec!["en"]);
+        assert!(fallback_chain("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_accept_language() {
+        assert_eq!(parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5"), vec!["fr-CH", "fr", "en", "de"]);
+        assert_eq!(parse_accept_language("en;q=0.5, zh-CN, ja;q=0"), vec!["zh-CN", "en"]);
+        assert!(parse_accept_language("").is_empty());
+    }
+}
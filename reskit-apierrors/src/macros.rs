@@ -1,3 +1,4 @@
+/// adapt_errorspace! adapts the error in the named errorspace, the name can be any `AsRef<str>`, e.g. `&str` or `String`
 #[macro_export]
 macro_rules! adapt_errorspace {
     ($name:expr, $error:expr, $meta:expr $(,$mapping:expr)* ) => {
@@ -16,12 +17,12 @@ macro_rules! adapt_errorspace {
     };
 }
 
-/// adapt! 
+/// adapt! adapts the error in the current errorspace, see `scope::current_errorspace`
 #[macro_export]
 macro_rules! adapt {
     ($error:expr, $meta:expr $(,$mapping:expr)* ) => {
         $crate::globals::adapt_errorspace(
-            $crate::GLOBAL_ERRORSPACE_NAME, 
+            $crate::scope::current_errorspace(), 
             $error, 
             $meta, 
             &[$($mapping,)*], 
@@ -35,6 +36,7 @@ macro_rules! adapt {
     };
 }
 
+/// force_errorspace! wraps the error with meta in the named errorspace, the name can be any `AsRef<str>`
 #[macro_export]
 macro_rules! force_errorspace {
     ($name:expr, $error:expr, $meta:expr $(,$mapping:expr)* ) => {
@@ -53,11 +55,12 @@ macro_rules! force_errorspace {
     };
 }
 
+/// force! wraps the error with meta in the current errorspace, see `scope::current_errorspace`
 #[macro_export]
 macro_rules! force {
     ($error:expr, $meta:expr $(,$mapping:expr)* ) => {
         $crate::globals::force_errorspace(
-            $crate::GLOBAL_ERRORSPACE_NAME, 
+            $crate::scope::current_errorspace(), 
             $error, 
             $meta, 
             &[$($mapping,)*], 
@@ -71,10 +74,49 @@ macro_rules! force {
     };
 }
 
+/// bail_api! returns early with an error built from the format args and wrapped with meta in the current errorspace
+#[macro_export]
+macro_rules! bail_api {
+    ($meta:expr, $($fmt:tt)+) => {
+        return ::core::result::Result::Err(::core::convert::From::from($crate::globals::force_errorspace(
+            $crate::scope::current_errorspace(),
+            $crate::__anyhow::anyhow!($($fmt)+),
+            $meta,
+            &[],
+            $crate::macros::select_caller($crate::__caller!()),
+        )))
+    };
+}
+
+/// ensure_api! returns early with `bail_api!` if the condition is false
+#[macro_export]
+macro_rules! ensure_api {
+    ($cond:expr, $meta:expr, $($fmt:tt)+) => {
+        if !$cond {
+            $crate::bail_api!($meta, $($fmt)+);
+        }
+    };
+}
+
+/// select_caller returns the caller recorded by the macros expanding it, None without the `verbose` feature
+/// 
+/// The feature is checked here rather than in the macros, where it would be the feature of the calling crate.
+#[doc(hidden)]
+#[cfg_attr(not(feature = "verbose"), allow(unused_variables))]
+pub fn select_caller(caller: &'static str) -> Option<&'static str> {
+    #[cfg(feature = "verbose")]
+    {
+        Some(caller)
+    }
+
+    #[cfg(not(feature = "verbose"))]
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use reskit_utils::init_once;
-    use crate::{Builtin, adapt_errorspace, adapt, };
+    use crate::{Builtin, APIError, APIErrorMeta, new_errorspace, with_errorspace};
 
     #[test]
     fn test_adapt_errorspace() {
@@ -85,6 +127,20 @@ mod tests {
         assert_eq!(format!("{}", e2), "500::1:Unknown error.:reskit_apierrors::macros::tests::test_adapt_errorspace->xxx");
     }
 
+    #[test]
+    fn test_adapt_errorspace_owned_name() {
+        init_once();
+        let name = String::from("");
+        let e = adapt_errorspace!(&name, anyhow::anyhow!("xxx"), &Builtin::Unknown);
+        assert_eq!(e.downcast_ref::<APIError>().unwrap().code(), "1");
+        let e2 = force_errorspace!(name, anyhow::anyhow!("xxx"), &Builtin::Internal);
+        let api_err = e2.downcast_ref::<APIError>().unwrap();
+        assert_eq!(api_err.code(), "2");
+        assert_eq!(format!("{:#}", api_err.error), "xxx");
+        #[cfg(feature = "verbose")]
+        assert_eq!(api_err.caller, Some("reskit_apierrors::macros::tests::test_adapt_errorspace_owned_name"));
+    }
+
     #[test]
     fn test_adapt() {
         init_once();
@@ -111,4 +167,37 @@ mod tests {
         let e2 = force!( anyhow::anyhow!("xxx"), &Builtin::Unknown, "authcar", "rest");
         assert_eq!(format!("{}", e2), "500::1:Unknown error.:reskit_apierrors::macros::tests::test_force->xxx");
     }
+
+    fn check_quota(used: u32, limit: u32) -> anyhow::Result<u32> {
+        ensure_api!(used <= limit, &Builtin::ResourceExhausted, "quota {} exceeds {}", used, limit);
+        if limit == 0 {
+            bail_api!(&Builtin::PermissionDenied, "no quota");
+        }
+        Ok(limit - used)
+    }
+
+    #[test]
+    fn test_bail_api() {
+        init_once();
+        assert_eq!(check_quota(1, 3).unwrap(), 2);
+        let e = check_quota(4, 3).unwrap_err();
+        let api_err = e.downcast_ref::<APIError>().unwrap();
+        assert_eq!(api_err.code(), Builtin::ResourceExhausted.code());
+        assert_eq!(format!("{:#}", api_err.error), "quota 4 exceeds 3");
+        #[cfg(feature = "verbose")]
+        assert_eq!(api_err.caller, Some("reskit_apierrors::macros::tests::check_quota"));
+        let e = check_quota(0, 0).unwrap_err();
+        assert_eq!(e.downcast_ref::<APIError>().unwrap().code(), Builtin::PermissionDenied.code());
+    }
+
+    #[test]
+    fn test_adapt_scoped() {
+        init_once();
+        new_errorspace("scoped-empty");
+        let inner = || force!(anyhow::anyhow!("xxx"), &Builtin::NotFound);
+        let e = adapt!(inner(), &Builtin::Unknown);
+        assert_eq!(e.downcast_ref::<APIError>().unwrap().code(), "7");
+        let e = with_errorspace("scoped-empty", || adapt!(inner(), &Builtin::Unknown));
+        assert_eq!(e.downcast_ref::<APIError>().unwrap().code(), "1");
+    }
 }
@@ -0,0 +1,255 @@
+//! Error code metrics, enabled by the `metrics` feature.
+//!
+//! Counters keyed by system, code, status_code, pvlost and optionally caller are incremented when
+//! `adapt!`/`force!` create an APIError and when errors are converted to responses. They are kept in
+//! the in-process `registry()`, which renders the Prometheus text format, and forwarded to the
+//! recorders added with `add_recorder`, e.g. `FacadeRecorder` for the `metrics` crate facade.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use lazy_static::lazy_static;
+
+use crate::APIErrorMeta;
+
+/// CREATED counts the api errors created by `adapt!`/`force!`
+pub const CREATED: &str = "apierrors_created_total";
+
+/// RESPONSES counts the api errors converted to responses
+pub const RESPONSES: &str = "apierrors_responses_total";
+
+static CALLER_LABEL: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+    static ref RECORDERS: RwLock<Vec<Arc<dyn Recorder>>> = RwLock::new(Vec::new());
+}
+
+/// Labels identify a counter of a metric
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Labels {
+    pub system: String,
+    pub code: String,
+    pub status_code: u16,
+    #[cfg(feature = "pvlost")]
+    pub pvlost: u8,
+    /// only set if the caller label is enabled, see `set_caller_label`
+    pub caller: Option<&'static str>,
+}
+
+impl Labels {
+    pub fn new(meta: &dyn APIErrorMeta, caller: Option<&'static str>) -> Labels {
+        Labels {
+            system: meta.system().to_string(),
+            code: meta.code().to_string(),
+            status_code: meta.status().code(),
+            #[cfg(feature = "pvlost")]
+            pvlost: meta.pvlost().into(),
+            caller,
+        }
+    }
+
+    /// pairs returns the label names and values in export order
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![
+            ("system", self.system.clone()),
+            ("code", self.code.clone()),
+            ("status_code", self.status_code.to_string()),
+        ];
+        #[cfg(feature = "pvlost")]
+        pairs.push(("pvlost", self.pvlost.to_string()));
+        if let Some(caller) = self.caller {
+            pairs.push(("caller", caller.to_string()));
+        }
+        pairs
+    }
+}
+
+/// Recorder receives the counter increments, implement it to forward them to another metrics system
+pub trait Recorder: Send + Sync {
+    fn increment(&self, name: &'static str, labels: &Labels);
+}
+
+/// Registry keeps the counters in process, by name then labels
+#[derive(Debug, Default)]
+pub struct Registry {
+    counters: Mutex<BTreeMap<&'static str, BTreeMap<Labels, u64>>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// get returns the value of the counter, 0 if it was never incremented
+    pub fn get(&self, name: &str, labels: &Labels) -> u64 {
+        let counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        counters.get(name).and_then(|counters| counters.get(labels)).copied().unwrap_or_default()
+    }
+
+    /// snapshot returns all counters sorted by name then labels
+    pub fn snapshot(&self) -> Vec<(&'static str, Labels, u64)> {
+        let counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        counters
+            .iter()
+            .flat_map(|(name, counters)| counters.iter().map(move |(labels, value)| (*name, labels.clone(), *value)))
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        self.counters.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// to_prometheus render the counters in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut current = "";
+        for (name, labels, value) in self.snapshot() {
+            if name != current {
+                writeln!(out, "# HELP {} {}", name, help(name)).unwrap();
+                writeln!(out, "# TYPE {} counter", name).unwrap();
+                current = name;
+            }
+            let labels: Vec<String> = labels
+                .pairs()
+                .into_iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(&value)))
+                .collect();
+            writeln!(out, "{}{{{}}} {}", name, labels.join(","), value).unwrap();
+        }
+        out
+    }
+}
+
+impl Recorder for Registry {
+    fn increment(&self, name: &'static str, labels: &Labels) {
+        let mut counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        let counters = counters.entry(name).or_default();
+        match counters.get_mut(labels) {
+            Some(value) => *value += 1,
+            None => {
+                counters.insert(labels.clone(), 1);
+            },
+        }
+    }
+}
+
+/// FacadeRecorder forwards the counters to the recorder installed in the `metrics` crate
+#[cfg(feature = "metrics-facade")]
+pub struct FacadeRecorder;
+
+#[cfg(feature = "metrics-facade")]
+impl Recorder for FacadeRecorder {
+    fn increment(&self, name: &'static str, labels: &Labels) {
+        let labels: Vec<metrics_crate::Label> = labels
+            .pairs()
+            .into_iter()
+            .map(|(key, value)| metrics_crate::Label::new(key, value))
+            .collect();
+        metrics_crate::counter!(name, labels).increment(1);
+    }
+}
+
+/// registry returns the in-process registry, which records every increment
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// add_recorder forward the increments to recorder too
+pub fn add_recorder(recorder: Arc<dyn Recorder>) {
+    RECORDERS.write().unwrap_or_else(PoisonError::into_inner).push(recorder);
+}
+
+/// set_caller_label enable or disable the caller label, disabled by default as it multiplies the counters
+pub fn set_caller_label(enabled: bool) {
+    CALLER_LABEL.store(enabled, Ordering::SeqCst);
+}
+
+/// record increment the counter name of meta in the registry and the added recorders
+pub(crate) fn record(name: &'static str, meta: &dyn APIErrorMeta, caller: Option<&'static str>) {
+    let caller = if CALLER_LABEL.load(Ordering::SeqCst) { caller } else { None };
+    let labels = Labels::new(meta, caller);
+    REGISTRY.increment(name, &labels);
+    for recorder in RECORDERS.read().unwrap_or_else(PoisonError::into_inner).iter() {
+        recorder.increment(name, &labels);
+    }
+}
+
+fn help(name: &str) -> &'static str {
+    match name {
+        CREATED => "API errors created by adapt! and force!.",
+        RESPONSES => "API errors converted to responses.",
+        _ => "API errors.",
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use reskit_utils::init_once;
+    use crate::{AsAPIErrorMeta, Builtin, force, prelude::*};
+    use super::{Labels, Recorder, Registry, CREATED, RESPONSES, registry, add_recorder};
+
+    #[derive(Debug, AsAPIErrorMeta)]
+    enum Metered {
+        #[apierrormeta(system="metrics_test", code="1", message="Counted \"error\".", status_code=400, pvlost="local_error")]
+        Counted,
+        #[apierrormeta(system="metrics_test", code="2", message="Rewrapped.", status_code=500)]
+        Rewrapped,
+    }
+
+    #[derive(Default)]
+    struct Forwarded(Mutex<Vec<String>>);
+
+    impl Recorder for Forwarded {
+        fn increment(&self, name: &'static str, labels: &Labels) {
+            if labels.system == "metrics_test" {
+                self.0.lock().unwrap().push(format!("{}:{}", name, labels.code));
+            }
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        init_once();
+        let forwarded = Arc::new(Forwarded::default());
+        add_recorder(forwarded.clone());
+        let _ = force!(anyhow::anyhow!("first"), &Metered::Counted);
+        let wrapped = force!(anyhow::anyhow!("second"), &Metered::Counted);
+        let wrapped = force!(wrapped, &Metered::Counted);
+        assert_eq!(registry().get(CREATED, &Labels::new(&Metered::Counted, None)), 2);
+        assert_eq!(forwarded.0.lock().unwrap().len(), 2);
+        let _ = force!(wrapped, &Metered::Rewrapped);
+        assert_eq!(registry().get(CREATED, &Labels::new(&Metered::Rewrapped, None)), 1);
+        assert_eq!(*forwarded.0.lock().unwrap(), ["apierrors_created_total:1", "apierrors_created_total:1", "apierrors_created_total:2"]);
+        #[cfg(feature = "serde")]
+        {
+            crate::response::meta_response(&Metered::Counted, None);
+            assert_eq!(registry().get(RESPONSES, &Labels::new(&Metered::Counted, None)), 1);
+        }
+        assert!(registry().to_prometheus().contains("# TYPE apierrors_created_total counter\n"));
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let registry = Registry::new();
+        registry.increment(CREATED, &Labels::new(&Builtin::NotFound, None));
+        registry.increment(CREATED, &Labels::new(&Builtin::NotFound, None));
+        registry.increment(CREATED, &Labels::new(&Builtin::Internal, Some("src/db.rs:1 \"q\"")));
+        let text = registry.to_prometheus();
+        assert!(text.starts_with("# HELP apierrors_created_total API errors created by adapt! and force!.\n# TYPE apierrors_created_total counter\n"));
+        #[cfg(not(feature = "pvlost"))]
+        {
+            assert!(text.contains("apierrors_created_total{system=\"\",code=\"2\",status_code=\"500\",caller=\"src/db.rs:1 \\\"q\\\"\"} 1\n"));
+            assert!(text.contains("apierrors_created_total{system=\"\",code=\"7\",status_code=\"404\"} 2\n"));
+        }
+        assert_eq!(text.matches("# TYPE").count(), 1);
+        registry.reset();
+        assert!(registry.snapshot().is_empty());
+    }
+}
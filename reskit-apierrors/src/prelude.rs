@@ -1,5 +1,7 @@
 //! The reskit-apierrors prelude.
 pub use crate::apierror::{APIErrorMeta, APIErrorMetas};
-
-#[cfg(feature = "pvlost")]
-pub use crate::pvlost::PVLost;
\ No newline at end of file
+pub use crate::ext::{OptionExt, ResultExt};
+pub use crate::retry::Retryable;
+pub use crate::severity::Severity;
+pub use crate::pvlost::PVLost;
+pub use crate::status_code::Status;
\ No newline at end of file
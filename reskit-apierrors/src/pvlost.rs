@@ -1,62 +1,172 @@
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug, Display};
 use std::convert::TryFrom;
+use std::option;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{RwLock, PoisonError};
+
+use http_types::headers::{HeaderName, HeaderValue, Headers, ToHeaderValues};
+use http_types::StatusCode;
+use lazy_static::lazy_static;
 
 /// PV Lost code, describe the pv status and use code to identify the lost reason.
-#[allow(unused_qualifications)]
-#[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-pub enum PVLost {
+///
+/// The builtin codes are 0 `Successful`, 1 `RemoteError` and 2 `LocalError`, more reasons can be
+/// registered with `PVLost::register`, e.g. dependency timeout or throttling.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PVLost(u8);
+
+#[allow(non_upper_case_globals)]
+impl PVLost {
     /// 0 Successful
     /// 
     /// Response header `pvlost: 0` return when the PV is successful, that is not lost.
-    Successful = 0,
+    pub const Successful: PVLost = PVLost(0);
 
     /// 1 Remote Error
     /// 
     /// Response header `pvlost: 1` return when the PV is lost because of remote error.
-    RemoteError = 1,
+    pub const RemoteError: PVLost = PVLost(1);
 
     /// 2 Local Error
     /// 
-    /// Response header `pvlost: 1` return when the PV is lost because of local error.
-    LocalError = 2,
+    /// Response header `pvlost: 2` return when the PV is lost because of local error.
+    pub const LocalError: PVLost = PVLost(2);
 }
 
+/// Reason describes a pvlost code
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Reason {
+    pub code: u8,
+    /// snake_case name, the derive accepts the names of the builtin reasons only, e.g. `pvlost="local_error"`
+    pub name: &'static str,
+    pub description: &'static str,
+    /// whether the PV counts as lost against availability
+    pub lost: bool,
+    /// whether the loss is caused locally rather than by a remote error, ignored if not lost
+    pub local: bool,
+}
+
+lazy_static! {
+    static ref REASONS: RwLock<BTreeMap<u8, Reason>> = RwLock::new(
+        [
+            Reason { code: 0, name: "successful", description: "Successful", lost: false, local: false },
+            Reason { code: 1, name: "remote_error", description: "Remote Error", lost: true, local: false },
+            Reason { code: 2, name: "local_error", description: "Local Error", lost: true, local: true },
+        ]
+        .iter()
+        .map(|reason| (reason.code, *reason))
+        .collect()
+    );
+}
+
+/// Policy infers the pvlost of metas which do not set it from their status code
+pub type Policy = fn(StatusCode) -> PVLost;
+
+static POLICY: RwLock<Policy> = RwLock::new(PVLost::from_status_code);
+
 impl PVLost {
+    /// Create a pvlost of code without checking it is registered.
+    pub const fn new(code: u8) -> PVLost {
+        PVLost(code)
+    }
+
+    pub fn code(&self) -> u8 {
+        self.0
+    }
+
+    /// The default policy by status class, successful below 400, local error for client errors and
+    /// remote error for server errors.
+    pub fn from_status_code(status_code: StatusCode) -> PVLost {
+        if status_code.is_client_error() {
+            PVLost::LocalError
+        } else if status_code.is_server_error() {
+            PVLost::RemoteError
+        } else {
+            PVLost::Successful
+        }
+    }
+
+    /// Infer the pvlost of status code with the global policy, see `set_policy`.
+    pub fn infer(status_code: StatusCode) -> PVLost {
+        let policy = *POLICY.read().unwrap_or_else(PoisonError::into_inner);
+        policy(status_code)
+    }
+
+    /// Set the global policy, `PVLost::from_status_code` by default.
+    ///
+    /// Metas which set `pvlost`, or whose enum sets `#[apierrormetas(pvlost=...)]` or
+    /// `#[apierrormetas(pvlost_policy="...")]`, are not affected.
+    pub fn set_policy(policy: Policy) {
+        *POLICY.write().unwrap_or_else(PoisonError::into_inner) = policy;
+    }
+
+    /// Register an additional reason, fails if the code or the name is registered already.
+    pub fn register(reason: Reason) -> anyhow::Result<PVLost> {
+        let mut reasons = REASONS.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(existing) = reasons.values().find(|r| r.code == reason.code || r.name == reason.name) {
+            anyhow::bail!("pvlost reason {}:{} conflicts with {}:{}", reason.code, reason.name, existing.code, existing.name);
+        }
+        reasons.insert(reason.code, reason);
+        Ok(PVLost(reason.code))
+    }
+
+    /// All the registered reasons sorted by code.
+    pub fn reasons() -> Vec<Reason> {
+        REASONS.read().unwrap_or_else(PoisonError::into_inner).values().copied().collect()
+    }
+
+    /// The registered reason of the code, None if it is not registered.
+    pub fn reason(&self) -> Option<Reason> {
+        REASONS.read().unwrap_or_else(PoisonError::into_inner).get(&self.0).copied()
+    }
+
+    /// Look a registered reason up by name.
+    pub fn from_name(name: &str) -> Option<PVLost> {
+        REASONS.read().unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .find(|reason| reason.name == name)
+            .map(|reason| PVLost(reason.code))
+    }
+
     /// Returns `true` if the pvlost == 0.
     ///
     /// If this returns `true` it indicates that the request was successfully
     /// received, understood, and accepted.
     pub fn is_success(&self) -> bool {
-        let num: u8 = self.clone().into();
-        num == 0
+        self.0 == 0
+    }
+
+    /// Returns `true` if the PV counts as lost against availability, unregistered codes are lost.
+    pub fn is_lost(&self) -> bool {
+        self.reason().map_or(!self.is_success(), |reason| reason.lost)
+    }
+
+    /// Returns `true` if the PV is lost because of a local error.
+    pub fn is_local(&self) -> bool {
+        self.reason().is_some_and(|reason| reason.lost && reason.local)
     }
 
     /// The description for a given pvlost code
     pub fn description(&self) -> &'static str {
-        match self {
-            PVLost::Successful => "Successful",
-            PVLost::RemoteError => "Remote Error",
-            PVLost::LocalError => "Local Error",
-        }
+        self.reason().map_or("Unknown", |reason| reason.description)
     }
 }
 
 impl From<PVLost> for u8 {
     fn from(code: PVLost) -> u8 {
-        code as u8
+        code.0
     }
 }
 
 impl TryFrom<&str> for PVLost {
     type Error = anyhow::Error;
 
+    /// Parse a registered code or name, e.g. "1" or "remote_error".
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "0" => Ok(PVLost::Successful),
-            "1" => Ok(PVLost::RemoteError),
-            "2" => Ok(PVLost::LocalError),
-            _ => anyhow::bail!("Invalid pvlost code"),
+        match value.parse::<u8>() {
+            Ok(num) => PVLost::try_from(num),
+            Err(_) => PVLost::from_name(value).ok_or_else(|| anyhow::anyhow!("Invalid pvlost code")),
         }
     }
 }
@@ -65,36 +175,104 @@ impl TryFrom<u8> for PVLost {
     type Error = anyhow::Error;
 
     fn try_from(num: u8) -> Result<Self, Self::Error> {
-        match num {
-            0 => Ok(PVLost::Successful),
-            1 => Ok(PVLost::RemoteError),
-            2 => Ok(PVLost::LocalError),
-            _ => anyhow::bail!("Invalid pvlost code"),
+        match PVLost(num).reason() {
+            Some(_) => Ok(PVLost(num)),
+            None => anyhow::bail!("Invalid pvlost code"),
         }
     }
 }
 
 impl PartialEq<PVLost> for u8 {
     fn eq(&self, other: &PVLost) -> bool {
-        *self == *other as u8
+        *self == other.0
     }
 }
 
 impl PartialEq<u8> for PVLost {
     fn eq(&self, other: &u8) -> bool {
-        *self as u8 == *other
+        self.0 == *other
     }
 }
 
 impl Debug for PVLost {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", *self as u8, self.description())
+        write!(f, "{}: {}", self.0, self.description())
     }
 }
 
 impl Display for PVLost {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", *self as u8, self.description())
+        write!(f, "{}: {}", self.0, self.description())
+    }
+}
+
+/// PVLOST is the name of the response header carrying the pvlost code
+pub const PVLOST: &str = "pvlost";
+
+static FALLBACK: AtomicU8 = AtomicU8::new(PVLost::RemoteError.0);
+
+/// PVLostHeader is the typed `pvlost` response header, e.g. `pvlost: 1`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PVLostHeader {
+    pvlost: PVLost,
+}
+
+impl PVLostHeader {
+    pub fn new(pvlost: PVLost) -> Self {
+        Self { pvlost }
+    }
+
+    pub fn pvlost(&self) -> PVLost {
+        self.pvlost
+    }
+
+    /// Parse the last `pvlost` header, a malformed value is a 400 error.
+    pub fn from_headers(headers: impl AsRef<Headers>) -> http_types::Result<Option<Self>> {
+        let headers = match headers.as_ref().get(PVLOST) {
+            Some(headers) => headers,
+            None => return Ok(None),
+        };
+        // there is always at least one value of a present header, the last one wins
+        let header = headers.iter().last().unwrap();
+        let pvlost = PVLost::try_from(header.as_str().trim()).map_err(|err| http_types::Error::new(400, err))?;
+        Ok(Some(Self { pvlost }))
+    }
+
+    /// Parse the last `pvlost` header, a malformed value is replaced by the fallback, see `set_fallback`.
+    pub fn from_headers_lossy(headers: impl AsRef<Headers>) -> Option<Self> {
+        headers.as_ref().get(PVLOST)?;
+        Some(Self::from_headers(headers).ok().flatten().unwrap_or(Self { pvlost: Self::fallback() }))
+    }
+
+    /// Set the pvlost malformed headers are parsed as, `RemoteError` by default.
+    pub fn set_fallback(pvlost: PVLost) {
+        FALLBACK.store(pvlost.into(), Ordering::SeqCst);
+    }
+
+    pub fn fallback() -> PVLost {
+        PVLost(FALLBACK.load(Ordering::SeqCst))
+    }
+
+    /// Insert the header, replacing existing values.
+    pub fn apply(&self, mut headers: impl AsMut<Headers>) {
+        headers.as_mut().insert(PVLOST, self.value());
+    }
+
+    pub fn name(&self) -> HeaderName {
+        HeaderName::from(PVLOST)
+    }
+
+    pub fn value(&self) -> HeaderValue {
+        // the code is always ascii digits
+        HeaderValue::from_bytes(u8::from(self.pvlost).to_string().into_bytes()).unwrap()
+    }
+}
+
+impl ToHeaderValues for PVLostHeader {
+    type Iter = option::IntoIter<HeaderValue>;
+
+    fn to_header_values(&self) -> http_types::Result<Self::Iter> {
+        Ok(Some(self.value()).into_iter())
     }
 }
 
@@ -103,6 +281,7 @@ mod serde {
     use super::PVLost;
     use serde_crate::de::{Error as DeError, Unexpected, Visitor};
     use serde_crate::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::convert::TryFrom;
     use std::fmt;
 
     impl Serialize for PVLost {
@@ -110,7 +289,7 @@ mod serde {
         where
             S: Serializer,
         {
-            let value: u8 = *self as u8;
+            let value: u8 = self.code();
             serializer.serialize_u8(value)
         }
     }
@@ -128,35 +307,38 @@ mod serde {
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Signed(i64::from(v)), &self))?;
+            self.visit_u8(v)
         }
 
         fn visit_i16<E>(self, v: i16) -> Result<Self::Value, E>
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Signed(i64::from(v)), &self))?;
+            self.visit_u8(v)
         }
 
         fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E>
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Signed(i64::from(v)), &self))?;
+            self.visit_u8(v)
         }
 
         fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Signed(v), &self))?;
+            self.visit_u8(v)
         }
 
         fn visit_u8<E>(self, v: u8) -> Result<Self::Value, E>
         where
             E: DeError,
         {
-            use std::convert::TryFrom;
             match PVLost::try_from(v) {
                 Ok(pvlost) => Ok(pvlost),
                 Err(_) => Err(DeError::invalid_value(
@@ -170,21 +352,24 @@ mod serde {
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Unsigned(u64::from(v)), &self))?;
+            self.visit_u8(v)
         }
 
         fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E>
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Unsigned(u64::from(v)), &self))?;
+            self.visit_u8(v)
         }
 
         fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
         where
             E: DeError,
         {
-            self.visit_u8(v as u8)
+            let v = u8::try_from(v).map_err(|_| DeError::invalid_value(Unexpected::Unsigned(v), &self))?;
+            self.visit_u8(v)
         }
     }
 
@@ -201,24 +386,29 @@ mod serde {
 #[cfg(test)]
 mod tests {
     use std::convert::{TryFrom, TryInto};
-    use super::PVLost;
+    use http_types::{Response, StatusCode};
+    use super::{PVLost, PVLostHeader, Reason, PVLOST};
     
+    #[cfg(feature = "serde")]
     #[test]
     fn test_serde_as_u8() -> Result<(), serde_json::Error> {
         let pvlost: PVLost = serde_json::from_str("2")?;
         assert_eq!(PVLost::LocalError, pvlost);
+        assert!(serde_json::from_str::<PVLost>("256").is_err());
+        assert!(serde_json::from_str::<PVLost>("-254").is_err());
         assert_eq!(
             Some(2),
-            serde_json::to_value(&PVLost::LocalError)?.as_u64()
+            serde_json::to_value(PVLost::LocalError)?.as_u64()
         );
         Ok(())
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants, clippy::single_match)]
     fn test_pvlost() {
-        assert_eq!(PVLost::Successful as i32, 0);
-        assert_eq!(PVLost::RemoteError as i32, 1);
-        assert_eq!(PVLost::LocalError as i32, 2);
+        assert_eq!(PVLost::Successful.code(), 0);
+        assert_eq!(PVLost::RemoteError.code(), 1);
+        assert_eq!(PVLost::LocalError.code(), 2);
         let result = PVLost::try_from("1");
         assert!(matches!(result, Ok(PVLost::RemoteError)));
         let result = PVLost::try_from("invalid");
@@ -238,4 +428,96 @@ mod tests {
         let result: Result<PVLost, anyhow::Error> = "0".try_into();
         assert!(matches!(result, Ok(PVLost::Successful)));
     }
+
+    #[test]
+    fn test_header() {
+        let mut res = Response::new(StatusCode::Ok);
+        assert_eq!(PVLostHeader::from_headers(&res).unwrap(), None);
+        assert_eq!(PVLostHeader::from_headers_lossy(&res), None);
+        PVLostHeader::new(PVLost::LocalError).apply(&mut res);
+        assert_eq!(res[PVLOST], "2");
+        assert_eq!(PVLostHeader::from_headers(&res).unwrap().unwrap().pvlost(), PVLost::LocalError);
+
+        res.insert_header(PVLOST, " 0 ");
+        assert_eq!(PVLostHeader::from_headers(&res).unwrap().unwrap().pvlost(), PVLost::Successful);
+        res.insert_header(PVLOST, "lost");
+        assert_eq!(PVLostHeader::from_headers(&res).unwrap_err().status(), 400);
+        assert_eq!(PVLostHeader::from_headers_lossy(&res).unwrap().pvlost(), PVLost::RemoteError);
+    }
+
+    #[test]
+    fn test_register() {
+        let throttled = PVLost::register(Reason { code: 10, name: "throttling", description: "Throttling", lost: true, local: true }).unwrap();
+        let aborted = PVLost::register(Reason { code: 11, name: "client_abort", description: "Client Abort", lost: false, local: false }).unwrap();
+        assert!(PVLost::register(Reason { code: 12, name: "throttling", description: "Again", lost: true, local: true }).is_err());
+        assert!(PVLost::register(Reason { code: 1, name: "remote", description: "Again", lost: true, local: false }).is_err());
+        assert_eq!(PVLost::try_from(10).unwrap(), throttled);
+        assert_eq!(PVLost::try_from("client_abort").unwrap(), aborted);
+        assert_eq!(PVLost::from_name("remote_error"), Some(PVLost::RemoteError));
+        assert!(throttled.is_lost() && throttled.is_local());
+        assert!(!aborted.is_lost() && !aborted.is_success());
+        assert_eq!(throttled.to_string(), "10: Throttling");
+        assert!(PVLost::new(200).is_lost());
+        assert_eq!(PVLost::new(200).description(), "Unknown");
+        assert!(PVLost::reasons().len() >= 5);
+    }
+
+    #[test]
+    fn test_derive() {
+        use crate::{APIErrorMeta, AsAPIErrorMeta};
+
+        #[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+        enum Named {
+            #[apierrormeta(system="pvlost_test", code="1", message="Canceled.", status_code=400, pvlost="local_error")]
+            Canceled,
+            #[apierrormeta(system="pvlost_test", code="2", message="Degraded.", status_code=200, pvlost=20)]
+            Degraded,
+            #[apierrormeta(system="pvlost_test", code="3", message="Shed.", status_code=503, pvlost=21)]
+            Shed,
+        }
+
+        assert_eq!(Named::Degraded.pvlost(), PVLost::new(20));
+        assert!(Named::Degraded.pvlost().is_lost());
+        PVLost::register(Reason { code: 20, name: "degradation", description: "Degradation", lost: false, local: false }).unwrap();
+        assert_eq!(Named::Canceled.pvlost(), PVLost::LocalError);
+        assert_eq!(Named::Degraded.pvlost(), PVLost::new(20));
+        assert!(!Named::Degraded.pvlost().is_lost());
+        assert_eq!(Named::Shed.pvlost(), PVLost::new(21));
+    }
+
+    #[test]
+    fn test_policy() {
+        use http_types::StatusCode;
+        use crate::{APIErrorMeta, AsAPIErrorMeta, Builtin};
+
+        assert_eq!(PVLost::from_status_code(StatusCode::Ok), PVLost::Successful);
+        assert_eq!(PVLost::from_status_code(StatusCode::NotFound), PVLost::LocalError);
+        assert_eq!(PVLost::from_status_code(StatusCode::BadGateway), PVLost::RemoteError);
+        assert_eq!(Builtin::InvalidArgument.pvlost(), PVLost::LocalError);
+
+        fn teapot(status_code: StatusCode) -> PVLost {
+            match status_code {
+                StatusCode::ImATeapot => PVLost::Successful,
+                _ => PVLost::from_status_code(status_code),
+            }
+        }
+        assert_eq!(teapot(StatusCode::ImATeapot), PVLost::Successful);
+        assert_eq!(teapot(StatusCode::Conflict), PVLost::LocalError);
+
+        #[derive(Clone, Copy, Debug, AsAPIErrorMeta)]
+        #[apierrormetas(pvlost_policy="teapot")]
+        enum Brewed {
+            #[apierrormeta(system="pvlost_test", code="11", message="Teapot.", status_code=418)]
+            Teapot,
+            #[apierrormeta(system="pvlost_test", code="12", message="Conflict.", status_code=409)]
+            Conflict,
+            #[apierrormeta(system="pvlost_test", code="13", message="Spilled.", status_code=418, pvlost="remote_error")]
+            Spilled,
+        }
+
+        assert_eq!(Brewed::Teapot.pvlost(), PVLost::Successful);
+        assert_eq!(Brewed::Conflict.pvlost(), PVLost::LocalError);
+        assert_eq!(Brewed::Spilled.pvlost(), PVLost::RemoteError);
+        assert_eq!(PVLost::infer(StatusCode::ImATeapot), PVLost::LocalError);
+    }
 }
@@ -0,0 +1,45 @@
+//! Redaction of internal error details.
+//!
+//! An `APIError` carries a public message, the message of its meta which is written for clients,
+//! and an internal detail, the caller and the `anyhow` chain which may leak implementation details.
+//! The `RedactionPolicy` of the current errorspace decides whether the chain is exposed by
+//! serializers and response converters, the caller is never exposed.
+use crate::APIErrorMeta;
+
+/// RedactionPolicy decides which errors expose their internal detail to clients
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RedactionPolicy {
+    /// hide the detail of every error, the default
+    #[default]
+    RedactAll,
+    /// hide the detail of 5xx errors, expose it for the others, e.g. validation failures of 4xx
+    RedactServerErrors,
+    /// expose the detail of every error, e.g. for internal services
+    ExposeAll,
+}
+
+impl RedactionPolicy {
+    /// redacts returns true if the detail of errors with the meta must be hidden
+    pub fn redacts(&self, meta: &dyn APIErrorMeta) -> bool {
+        match self {
+            RedactionPolicy::RedactAll => true,
+            RedactionPolicy::RedactServerErrors => meta.status_code().is_server_error(),
+            RedactionPolicy::ExposeAll => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Builtin;
+    use super::RedactionPolicy;
+
+    #[test]
+    fn test_redacts() {
+        assert_eq!(RedactionPolicy::default(), RedactionPolicy::RedactAll);
+        assert!(RedactionPolicy::RedactAll.redacts(&Builtin::InvalidArgument));
+        assert!(!RedactionPolicy::RedactServerErrors.redacts(&Builtin::InvalidArgument));
+        assert!(RedactionPolicy::RedactServerErrors.redacts(&Builtin::Internal));
+        assert!(!RedactionPolicy::ExposeAll.redacts(&Builtin::Internal));
+    }
+}
@@ -0,0 +1,424 @@
+//! HTTP response conversion.
+//!
+//! Errors are rendered as a JSON envelope with the status code of the meta, the message is
+//! localized by the `Accept-Language` of the request when the current errorspace has bundles.
+//! The error chain is only rendered as `detail` when the redaction policy of the errorspace of the
+//! error exposes it, see `redaction::RedactionPolicy`. Custom status codes, e.g. 499, are sent
+//! as the standard code of their class with the custom code in the `status` field of the envelope.
+//! Clients decode such responses with `decode_response`.
+use std::borrow::Cow;
+
+use http_types::headers::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, RETRY_AFTER};
+use http_types::{Body, Request, Response};
+use serde_crate::{Deserialize, Serialize, Serializer};
+#[cfg(feature = "pvlost")]
+use serde_crate::Deserializer;
+
+use crate::apierror::negotiate_message;
+use crate::{APIError, APIErrorMeta, Builtin, Retryable, Status, current_errorspace, get_api_error_meta_errorspace, global_code_errorspace};
+
+#[cfg(feature = "pvlost")]
+use crate::pvlost::{PVLost, PVLostHeader};
+
+/// ErrorBody is the JSON envelope of an error response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(crate = "serde_crate")]
+pub struct ErrorBody<'a> {
+    pub system: &'a str,
+    pub code: &'a str,
+    /// the numeric code unique across systems, if the system reserves codes, see `Errorspace::global_code`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_code: Option<u64>,
+    pub message: Cow<'a, str>,
+    /// the custom status code, e.g. 499, the response carries the standard code of its class
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[cfg(feature = "pvlost")]
+    pub pvlost: u8,
+    pub retryable: Retryable,
+    /// seconds to wait before retrying, also sent as the `Retry-After` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl<'a> ErrorBody<'a> {
+    /// new build the envelope of meta, the global code is by the codes reserved in the errorspace
+    fn new(meta: &'a dyn APIErrorMeta, errorspace: &str, message: Cow<'a, str>, detail: Option<String>) -> ErrorBody<'a> {
+        ErrorBody {
+            system: meta.system(),
+            code: meta.code(),
+            global_code: global_code_errorspace(errorspace, meta),
+            message,
+            status: Some(meta.status()).filter(|status| !status.is_standard()).map(|status| status.code()),
+            #[cfg(feature = "pvlost")]
+            pvlost: meta.pvlost().into(),
+            retryable: meta.retryable(),
+            retry_after: meta.retry_after().map(|after| after.as_secs()),
+            detail,
+        }
+    }
+}
+
+/// meta_response render the api error meta as a response, with the message negotiated by accept_language
+pub fn meta_response(meta: &dyn APIErrorMeta, accept_language: Option<&str>) -> Response {
+    render(meta, &current_errorspace(), None, None, accept_language)
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn render(meta: &dyn APIErrorMeta, errorspace: &str, caller: Option<&'static str>, detail: Option<String>, accept_language: Option<&str>) -> Response {
+    #[cfg(feature = "metrics")]
+    crate::metrics::record(crate::metrics::RESPONSES, meta, caller);
+    let (message, language) = match accept_language {
+        Some(accept_language) => negotiate_message(meta, errorspace, accept_language),
+        None => (Cow::Borrowed(meta.message()), None),
+    };
+    let body = ErrorBody::new(meta, errorspace, message, detail);
+    let mut res = Response::new(meta.status_code());
+    // NOTE: the envelope only has strings and integers, serializing it never fails
+    res.set_body(Body::from_json(&body).unwrap_or_else(|_| Body::empty()));
+    if let Some(language) = language {
+        res.insert_header(CONTENT_LANGUAGE, language);
+    }
+    if let Some(retry_after) = body.retry_after {
+        res.insert_header(RETRY_AFTER, retry_after.to_string());
+    }
+    #[cfg(feature = "pvlost")]
+    PVLostHeader::new(meta.pvlost()).apply(&mut res);
+    res
+}
+
+/// error_response render the anyhow::Error as a response, errors which are not APIError are rendered as `Builtin::Unknown`
+pub fn error_response(err: &anyhow::Error, accept_language: Option<&str>) -> Response {
+    match err.downcast_ref::<APIError>() {
+        Some(api_err) => api_err.to_response(accept_language),
+        None => meta_response(&Builtin::Unknown, accept_language),
+    }
+}
+
+impl<'a> APIError<'a> {
+    /// to_response render the error as a response, with the message negotiated by accept_language
+    pub fn to_response(&self, accept_language: Option<&str>) -> Response {
+        render(self, &self.errorspace_name(), self.caller, self.detail(), accept_language)
+    }
+
+    /// to_response_for render the error as a response to req, negotiating the message by its `Accept-Language`
+    pub fn to_response_for(&self, req: &Request) -> Response {
+        self.to_response(req.header(ACCEPT_LANGUAGE).map(|values| values.as_str()))
+    }
+}
+
+impl<'a> From<APIError<'a>> for Response {
+    fn from(err: APIError<'a>) -> Response {
+        err.to_response(None)
+    }
+}
+
+/// APIError is serialized as the envelope of error responses, redacted by the policy of the errorspace of the error
+impl<'a> Serialize for APIError<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ErrorBody::new(self, &self.errorspace_name(), Cow::Borrowed(self.public_message()), self.detail()).serialize(serializer)
+    }
+}
+
+/// DecodedError is an error response decoded by a client
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub struct DecodedError {
+    /// status code of the response
+    #[serde(skip)]
+    pub status_code: u16,
+    #[serde(default)]
+    pub system: String,
+    pub code: String,
+    #[serde(default)]
+    pub global_code: Option<u64>,
+    #[serde(default)]
+    pub message: String,
+    /// the custom status code of the envelope, if any
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// the `pvlost` header, or the envelope field if the header is absent, an invalid field is read as the fallback
+    #[cfg(feature = "pvlost")]
+    #[serde(default = "PVLostHeader::fallback", deserialize_with = "deserialize_pvlost_lossy")]
+    pub pvlost: PVLost,
+    #[serde(default)]
+    pub retryable: Retryable,
+    #[serde(default)]
+    pub retry_after: Option<u64>,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+impl DecodedError {
+    /// status returns the custom status code of the envelope, or the status code of the response
+    pub fn status(&self) -> Status {
+        Status::new(self.status.unwrap_or(self.status_code))
+    }
+
+    /// meta returns the api error meta of system:code registered in the current errorspace, if any
+    pub fn meta(&self) -> Option<&'static dyn APIErrorMeta> {
+        get_api_error_meta_errorspace(&current_errorspace(), &self.system, &self.code)
+    }
+}
+
+/// deserialize_pvlost_lossy read an invalid `pvlost` field as `PVLostHeader::fallback`, like a malformed header
+#[cfg(feature = "pvlost")]
+fn deserialize_pvlost_lossy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PVLost, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(crate = "serde_crate", untagged)]
+    enum Lossy {
+        Valid(PVLost),
+        Invalid(serde_crate::de::IgnoredAny),
+    }
+
+    Ok(match Lossy::deserialize(deserializer)? {
+        Lossy::Valid(pvlost) => pvlost,
+        Lossy::Invalid(_) => PVLostHeader::fallback(),
+    })
+}
+
+/// decode_response decode the error envelope of a response, a malformed `pvlost` header or field is read as the fallback
+pub async fn decode_response(res: &mut Response) -> http_types::Result<DecodedError> {
+    let mut decoded: DecodedError = res.body_json().await?;
+    decoded.status_code = res.status() as u16;
+    #[cfg(feature = "pvlost")]
+    if let Some(header) = PVLostHeader::from_headers_lossy(&*res) {
+        decoded.pvlost = header.pvlost();
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+    use http_types::{Method, Request, Response, StatusCode, Url};
+    use reskit_utils::init_once;
+    use crate::{APIError, Builtin, CodeRange, RedactionPolicy, force, new_errorspace, register_messages_errorspace, reserve_codes_errorspace, set_redaction_errorspace, with_errorspace};
+    use crate::locale::MessageBundle;
+    use super::{error_response, decode_response, meta_response};
+
+    fn api_error() -> APIError<'static> {
+        APIError { meta: &Builtin::NotFound, error: anyhow::anyhow!("user 42"), caller: None, errorspace: None }
+    }
+
+    fn body(mut res: Response) -> serde_json::Value {
+        block_on(res.body_json()).unwrap()
+    }
+
+    #[test]
+    fn test_localized_message() {
+        init_once();
+        new_errorspace("response-localized");
+        register_messages_errorspace("response-localized", "zh", MessageBundle::new().with("", "7", "资源不存在。")).unwrap();
+        let err = api_error();
+        assert_eq!(err.localized_message("zh-CN"), "Not found.");
+        with_errorspace("response-localized", || {
+            assert_eq!(err.localized_message("zh-CN"), "资源不存在。");
+            assert_eq!(err.localized_message("en"), "Not found.");
+            let (message, language) = err.negotiate_message("en;q=0.5, zh-CN");
+            assert_eq!(message, "资源不存在。");
+            assert_eq!(language.unwrap(), "zh");
+            assert_eq!(err.negotiate_message("en").1, None);
+        });
+
+        // the errorspace the error was forced in wins over the current one
+        let err = with_errorspace("response-localized", || force!(anyhow::anyhow!("user 42"), &Builtin::NotFound));
+        let err = err.downcast_ref::<APIError>().unwrap();
+        assert_eq!(err.errorspace.as_deref(), Some("response-localized"));
+        assert_eq!(err.localized_message("zh-CN"), "资源不存在。");
+        let res = err.to_response(Some("zh-CN"));
+        assert_eq!(res.header("content-language").unwrap(), "zh");
+        assert_eq!(body(res)["message"], "资源不存在。");
+    }
+
+    #[test]
+    fn test_response() {
+        init_once();
+        let res = api_error().to_response(None);
+        assert_eq!(res.status(), StatusCode::NotFound);
+        assert!(res.header("content-language").is_none());
+        let value = body(res);
+        assert_eq!(value["system"], "");
+        assert_eq!(value["code"], "7");
+        assert_eq!(value["message"], "Not found.");
+
+        let res = error_response(&anyhow::anyhow!("plain"), None);
+        assert_eq!(res.status(), StatusCode::InternalServerError);
+        assert_eq!(body(res)["code"], "1");
+        let res = error_response(&anyhow::Error::new(api_error()), None);
+        assert_eq!(res.status(), StatusCode::NotFound);
+        let res: Response = api_error().into();
+        assert_eq!(res.status(), StatusCode::NotFound);
+    }
+
+    #[test]
+    fn test_response_accept_language() {
+        init_once();
+        new_errorspace("response-accept-language");
+        register_messages_errorspace("response-accept-language", "zh", MessageBundle::new().with("", "7", "资源不存在。")).unwrap();
+        let mut req = Request::new(Method::Get, Url::parse("http://localhost/users/42").unwrap());
+        req.insert_header("Accept-Language", "zh-CN, en;q=0.8");
+        let res = with_errorspace("response-accept-language", || api_error().to_response_for(&req));
+        assert_eq!(res.header("content-language").unwrap().as_str(), "zh");
+        assert_eq!(body(res)["message"], "资源不存在。");
+        let res = api_error().to_response_for(&req);
+        assert_eq!(body(res)["message"], "Not found.");
+    }
+
+    #[test]
+    fn test_redaction() {
+        init_once();
+        new_errorspace("response-redaction");
+        set_redaction_errorspace("response-redaction", Some(RedactionPolicy::RedactServerErrors)).unwrap();
+        let internal = APIError { meta: &Builtin::Internal, error: anyhow::anyhow!("db password wrong"), caller: Some("src/db.rs:42"), errorspace: None };
+        let not_found = api_error();
+        assert_eq!(internal.public_message(), "Internal server error.");
+        assert_eq!(internal.internal_detail(), "src/db.rs:42->db password wrong");
+        assert!(not_found.is_redacted());
+        assert!(body(not_found.to_response(None)).get("detail").is_none());
+        assert!(serde_json::to_value(&not_found).unwrap().get("detail").is_none());
+
+        with_errorspace("response-redaction", || {
+            assert_eq!(not_found.detail().unwrap(), "user 42");
+            assert_eq!(body(not_found.to_response(None))["detail"], "user 42");
+            assert_eq!(serde_json::to_value(&not_found).unwrap()["detail"], "user 42");
+            assert!(internal.detail().is_none());
+            let value = body(internal.to_response(None));
+            assert!(value.get("detail").is_none());
+            assert!(!value.to_string().contains("src/db.rs"));
+        });
+    }
+
+    #[test]
+    fn test_redaction_errorspace() {
+        init_once();
+        new_errorspace("response-redact-all");
+        set_redaction_errorspace("response-redact-all", Some(RedactionPolicy::RedactAll)).unwrap();
+        new_errorspace("response-expose-all");
+        set_redaction_errorspace("response-expose-all", Some(RedactionPolicy::ExposeAll)).unwrap();
+
+        // the policy of the errorspace the error was forced in wins over the current one
+        let redacted = with_errorspace("response-redact-all", || force!(anyhow::anyhow!("user 42"), &Builtin::NotFound));
+        let redacted = redacted.downcast_ref::<APIError>().unwrap();
+        let exposed = with_errorspace("response-expose-all", || force!(anyhow::anyhow!("db password wrong"), &Builtin::Internal));
+        let exposed = exposed.downcast_ref::<APIError>().unwrap();
+        with_errorspace("response-expose-all", || {
+            assert!(redacted.is_redacted());
+            assert!(body(redacted.to_response(None)).get("detail").is_none());
+            assert!(serde_json::to_value(redacted).unwrap().get("detail").is_none());
+        });
+        assert!(redacted.detail().is_none());
+        with_errorspace("response-redact-all", || {
+            assert_eq!(exposed.detail().unwrap(), "db password wrong");
+            assert_eq!(body(exposed.to_response(None))["detail"], "db password wrong");
+        });
+    }
+
+    #[test]
+    fn test_global_code() {
+        init_once();
+        new_errorspace("response-global-code");
+        reserve_codes_errorspace("response-global-code", "", CodeRange::new(3, 0..=99)).unwrap();
+        let err = with_errorspace("response-global-code", || force!(anyhow::anyhow!("user 42"), &Builtin::NotFound));
+        let err = err.downcast_ref::<APIError>().unwrap();
+        assert_eq!(err.global_code(), Some(30_007));
+        assert_eq!(body(err.to_response(None))["global_code"], 30_007);
+        assert_eq!(serde_json::to_value(err).unwrap()["global_code"], 30_007);
+
+        // bare metas have the global code of the current errorspace
+        assert!(body(meta_response(&Builtin::NotFound, None)).get("global_code").is_none());
+        let res = with_errorspace("response-global-code", || meta_response(&Builtin::NotFound, None));
+        assert_eq!(body(res)["global_code"], 30_007);
+    }
+
+    #[test]
+    fn test_nested_detail() {
+        init_once();
+        new_errorspace("response-nested");
+        set_redaction_errorspace("response-nested", Some(RedactionPolicy::ExposeAll)).unwrap();
+        with_errorspace("response-nested", || {
+            let inner = force!(anyhow::anyhow!("user 42"), &Builtin::NotFound);
+            let outer = force!(inner.context("load profile"), &Builtin::Internal);
+            let outer = outer.downcast_ref::<APIError>().unwrap();
+            assert_eq!(outer.detail().unwrap(), "load profile: Not found.: user 42");
+            let nested = force!(force!(anyhow::anyhow!("user 42"), &Builtin::NotFound), &Builtin::Internal);
+            let nested = nested.downcast_ref::<APIError>().unwrap();
+            assert_eq!(nested.detail().unwrap(), "Not found.: user 42");
+            let value = body(nested.to_response(None));
+            assert_eq!(value["detail"], "Not found.: user 42");
+            assert!(!value.to_string().contains("reskit_apierrors"));
+        });
+    }
+
+    #[test]
+    fn test_retry() {
+        init_once();
+        let res = api_error().to_response(None);
+        assert!(res.header("retry-after").is_none());
+        let value = body(res);
+        assert_eq!(value["retryable"], "never");
+        assert!(value.get("retry_after").is_none());
+
+        let unavailable = APIError { meta: &Builtin::Unavailable, error: anyhow::anyhow!("upstream down"), caller: None, errorspace: None };
+        let res = unavailable.to_response(None);
+        assert_eq!(res.header("retry-after").unwrap().as_str(), "1");
+        let value = body(res);
+        assert_eq!(value["retryable"], "idempotent_only");
+        assert_eq!(value["retry_after"], 1);
+    }
+
+    #[test]
+    fn test_decode_response() {
+        init_once();
+        let mut res = APIError { meta: &Builtin::Unavailable, error: anyhow::anyhow!("upstream down"), caller: None, errorspace: None }.to_response(None);
+        let decoded = block_on(decode_response(&mut res)).unwrap();
+        assert_eq!(decoded.status_code, 503);
+        assert_eq!((decoded.system.as_str(), decoded.code.as_str()), ("", "26"));
+        assert_eq!(decoded.message, "Service unavailable.");
+        assert_eq!(decoded.retry_after, Some(1));
+        assert_eq!(decoded.meta().unwrap().code(), "26");
+
+        let mut res = Response::new(StatusCode::BadGateway);
+        res.set_body(serde_json::json!({"code": "99"}));
+        let decoded = block_on(decode_response(&mut res)).unwrap();
+        assert_eq!(decoded.code, "99");
+        assert!(decoded.meta().is_none());
+        assert!(block_on(decode_response(&mut Response::new(StatusCode::BadGateway))).is_err());
+    }
+
+    #[test]
+    fn test_custom_status() {
+        init_once();
+        let mut res = APIError { meta: &Builtin::Cancelled, error: anyhow::anyhow!("closed"), caller: None, errorspace: None }.to_response(None);
+        assert_eq!(res.status(), StatusCode::BadRequest);
+        let decoded = block_on(decode_response(&mut res)).unwrap();
+        assert_eq!(decoded.status, Some(499));
+        assert_eq!(decoded.status(), 499);
+        assert_eq!(body(meta_response(&Builtin::NotFound, None)).get("status"), None);
+    }
+
+    #[cfg(feature = "pvlost")]
+    #[test]
+    fn test_pvlost_header() {
+        use crate::PVLost;
+        init_once();
+        let mut res = api_error().to_response(None);
+        assert_eq!(res["pvlost"], "2");
+        res.insert_header("pvlost", "lost");
+        assert_eq!(block_on(decode_response(&mut res)).unwrap().pvlost, PVLost::RemoteError);
+
+        let mut res = Response::new(StatusCode::BadRequest);
+        res.set_body(serde_json::json!({"code": "9", "pvlost": 2}));
+        assert_eq!(block_on(decode_response(&mut res)).unwrap().pvlost, PVLost::LocalError);
+
+        for invalid in [serde_json::json!("lost"), serde_json::json!(99), serde_json::json!(256), serde_json::json!(-255), serde_json::json!(null), serde_json::json!({"code": 2})] {
+            let mut res = Response::new(StatusCode::BadRequest);
+            res.set_body(serde_json::json!({"code": "9", "message": "Bad.", "pvlost": invalid}));
+            let decoded = block_on(decode_response(&mut res)).unwrap();
+            assert_eq!(decoded.pvlost, PVLost::RemoteError);
+            assert_eq!(decoded.message, "Bad.");
+        }
+    }
+}
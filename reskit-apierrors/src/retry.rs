@@ -0,0 +1,90 @@
+//! Retry semantics of api error metas.
+//!
+//! Metas classify whether a failed request may be retried by clients, optionally with a hint of how
+//! long to wait, so clients implement one retry policy instead of matching codes:
+//!
+//! ```ignore
+//! #[apierrormeta(system="", code="26", message="Service unavailable.", status_code=503, retryable="idempotent_only", retry_after=1)]
+//! Unavailable,
+//! ```
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Retryable tells whether a request failed with the error may be retried
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate", rename_all = "snake_case"))]
+pub enum Retryable {
+    /// the request must not be retried until something is fixed, the default
+    #[default]
+    Never,
+    /// the request can be retried as is, e.g. the operation was aborted without side effects
+    Safe,
+    /// the request can be retried only if it is idempotent, the operation may have been applied
+    IdempotentOnly,
+}
+
+impl Retryable {
+    /// allows returns true if a request, idempotent or not, may be retried
+    pub fn allows(&self, idempotent: bool) -> bool {
+        match self {
+            Retryable::Never => false,
+            Retryable::Safe => true,
+            Retryable::IdempotentOnly => idempotent,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Retryable::Never => "never",
+            Retryable::Safe => "safe",
+            Retryable::IdempotentOnly => "idempotent_only",
+        }
+    }
+}
+
+impl Display for Retryable {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Retryable {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Retryable::Never),
+            "safe" => Ok(Retryable::Safe),
+            "idempotent_only" => Ok(Retryable::IdempotentOnly),
+            _ => anyhow::bail!("Invalid retryable {:?}", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::{Builtin, APIErrorMeta};
+    use super::Retryable;
+
+    #[test]
+    fn test_retryable() {
+        assert!(!Retryable::Never.allows(true));
+        assert!(Retryable::Safe.allows(false));
+        assert!(Retryable::IdempotentOnly.allows(true));
+        assert!(!Retryable::IdempotentOnly.allows(false));
+        assert_eq!("idempotent_only".parse::<Retryable>().unwrap(), Retryable::IdempotentOnly);
+        assert!("always".parse::<Retryable>().is_err());
+        assert_eq!(Retryable::Safe.to_string(), "safe");
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert_eq!(Builtin::Internal.retryable(), Retryable::Never);
+        assert_eq!(Builtin::Internal.retry_after(), None);
+        assert_eq!(Builtin::Aborted.retryable(), Retryable::Safe);
+        assert_eq!(Builtin::Unavailable.retryable(), Retryable::IdempotentOnly);
+        assert_eq!(Builtin::Unavailable.retry_after(), Some(Duration::from_secs(1)));
+    }
+}
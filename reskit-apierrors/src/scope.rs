@@ -0,0 +1,142 @@
+//! Scoped active errorspace.
+//!
+//! `adapt!` and `force!` resolve against the current errorspace, which is the innermost
+//! errorspace entered on this thread, or `GLOBAL_ERRORSPACE_NAME` if none. A middleware
+//! can pick the errorspace per request, e.g. by the calling partner:
+//!
+//! ```ignore
+//! let response = handler(req).in_errorspace(partner.errorspace_name()).await;
+//! ```
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use crate::{ErrorspaceName, GLOBAL_ERRORSPACE_NAME};
+
+thread_local! {
+    static CURRENT: RefCell<Vec<ErrorspaceName>> = const { RefCell::new(Vec::new()) };
+}
+
+/// current_errorspace returns the innermost entered errorspace name of this thread, or GLOBAL_ERRORSPACE_NAME
+pub fn current_errorspace() -> ErrorspaceName {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .last()
+            .cloned()
+            .unwrap_or(ErrorspaceName::Borrowed(GLOBAL_ERRORSPACE_NAME))
+    })
+}
+
+/// enter_errorspace makes name the current errorspace of this thread until the guard is dropped
+pub fn enter_errorspace(name: impl Into<ErrorspaceName>) -> ErrorspaceGuard {
+    let depth = CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        current.push(name.into());
+        current.len() - 1
+    });
+    ErrorspaceGuard { depth, _not_send: PhantomData }
+}
+
+/// with_errorspace runs f with name as the current errorspace of this thread
+pub fn with_errorspace<R>(name: impl Into<ErrorspaceName>, f: impl FnOnce() -> R) -> R {
+    let _guard = enter_errorspace(name);
+    f()
+}
+
+/// ErrorspaceGuard restores the errorspace current before it was created when dropped
+/// 
+/// Dropping an outer guard first also leaves the errorspaces entered after it.
+#[must_use = "the errorspace is left as soon as the guard is dropped"]
+pub struct ErrorspaceGuard {
+    // the stack depth before entering, restored on drop
+    depth: usize,
+    // the guard truncates the stack of the thread that created it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ErrorspaceGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| {
+            current.borrow_mut().truncate(self.depth);
+        });
+    }
+}
+
+pin_project! {
+    /// InErrorspace is a future which polls the inner future with the errorspace entered
+    pub struct InErrorspace<F> {
+        #[pin]
+        inner: F,
+        name: ErrorspaceName,
+    }
+}
+
+impl<F: Future> Future for InErrorspace<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _guard = enter_errorspace(this.name.clone());
+        this.inner.poll(cx)
+    }
+}
+
+/// ErrorspaceFutureExt scopes the current errorspace to a future, i.e. an async task-local errorspace
+pub trait ErrorspaceFutureExt: Future + Sized {
+    /// in_errorspace makes name the current errorspace whenever the future is polled
+    fn in_errorspace(self, name: impl Into<ErrorspaceName>) -> InErrorspace<Self> {
+        InErrorspace {
+            inner: self,
+            name: name.into(),
+        }
+    }
+}
+
+impl<F: Future> ErrorspaceFutureExt for F {}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::{block_on, yield_now};
+    use super::{current_errorspace, enter_errorspace, with_errorspace, ErrorspaceFutureExt};
+
+    #[test]
+    fn test_thread_scope() {
+        assert_eq!(current_errorspace(), "");
+        with_errorspace("outer", || {
+            assert_eq!(current_errorspace(), "outer");
+            {
+                let _guard = enter_errorspace(format!("inner-{}", 1));
+                assert_eq!(current_errorspace(), "inner-1");
+            }
+            assert_eq!(current_errorspace(), "outer");
+        });
+        assert_eq!(current_errorspace(), "");
+    }
+
+    #[test]
+    fn test_out_of_order_drop() {
+        let outer = enter_errorspace("outer");
+        let inner = enter_errorspace("inner");
+        drop(outer);
+        assert_eq!(current_errorspace(), "");
+        drop(inner);
+        assert_eq!(current_errorspace(), "");
+        let _guard = enter_errorspace("again");
+        assert_eq!(current_errorspace(), "again");
+    }
+
+    #[test]
+    fn test_future_scope() {
+        let name = block_on(async {
+            yield_now().await;
+            current_errorspace()
+        }.in_errorspace("partner"));
+        assert_eq!(name, "partner");
+        assert_eq!(current_errorspace(), "");
+    }
+}
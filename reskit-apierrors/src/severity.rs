@@ -0,0 +1,187 @@
+//! Severity of api error metas.
+//!
+//! The severity decides the level errors are logged at, it is inferred from the status code class,
+//! and pvlost if enabled, unless the meta overrides it, e.g. `#[apierrormeta(..., severity="critical")]`.
+//! With the `log` feature `APIError::log` writes the error at the level of its severity.
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use http_types::StatusCode;
+
+use crate::APIErrorMeta;
+
+#[cfg(feature = "pvlost")]
+use crate::PVLost;
+
+/// Severity classifies how serious an error is, ordered from the least serious
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_crate", rename_all = "snake_case"))]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    /// from_status_code returns debug for success, info for client errors and error for server errors
+    pub fn from_status_code(status_code: StatusCode) -> Severity {
+        if status_code.is_server_error() {
+            Severity::Error
+        } else if status_code.is_client_error() {
+            Severity::Info
+        } else {
+            Severity::Debug
+        }
+    }
+
+    /// with_pvlost cap the severity by pvlost, info if the pv is not lost and warn if it is lost by a local error
+    #[cfg(feature = "pvlost")]
+    pub fn with_pvlost(self, pvlost: PVLost) -> Severity {
+        if !pvlost.is_lost() {
+            self.min(Severity::Info)
+        } else if pvlost.is_local() {
+            self.min(Severity::Warn)
+        } else {
+            self
+        }
+    }
+
+    /// infer returns the default severity of the meta, ignoring the severity it overrides
+    pub fn infer(meta: &dyn APIErrorMeta) -> Severity {
+        let severity = Severity::from_status_code(meta.status_code());
+        #[cfg(feature = "pvlost")]
+        let severity = severity.with_pvlost(meta.pvlost());
+        severity
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// log_level returns the level of the log crate, critical is logged as error
+    #[cfg(feature = "log")]
+    pub fn log_level(&self) -> log::Level {
+        match self {
+            Severity::Debug => log::Level::Debug,
+            Severity::Info => log::Level::Info,
+            Severity::Warn => log::Level::Warn,
+            Severity::Error | Severity::Critical => log::Level::Error,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debug" => Ok(Severity::Debug),
+            "info" => Ok(Severity::Info),
+            "warn" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            "critical" => Ok(Severity::Critical),
+            _ => anyhow::bail!("Invalid severity {:?}", s),
+        }
+    }
+}
+
+/// log_error log the anyhow::Error at the severity of its api error meta, errors which are not APIError as error
+#[cfg(feature = "log")]
+pub fn log_error(err: &anyhow::Error) {
+    match err.downcast_ref::<crate::APIError>() {
+        Some(api_err) => api_err.log(),
+        None => log::error!(target: "reskit_apierrors", "{:#}", err),
+    }
+}
+
+#[cfg(feature = "log")]
+impl<'a> crate::APIError<'a> {
+    /// log write the error with its caller and chain at the level of its severity
+    pub fn log(&self) {
+        let severity = self.severity();
+        log::log!(target: "reskit_apierrors", severity.log_level(), "[{}] {}", severity, self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_types::StatusCode;
+    use crate::{Builtin, APIErrorMeta};
+    use super::Severity;
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(Severity::from_status_code(StatusCode::Ok), Severity::Debug);
+        assert_eq!(Severity::from_status_code(StatusCode::NotFound), Severity::Info);
+        assert_eq!(Severity::from_status_code(StatusCode::BadGateway), Severity::Error);
+        assert!(Severity::Critical > Severity::Error);
+        assert_eq!("warn".parse::<Severity>().unwrap(), Severity::Warn);
+        assert!("fatal".parse::<Severity>().is_err());
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert_eq!(Builtin::Successful.severity(), Severity::Debug);
+        assert_eq!(Builtin::InvalidArgument.severity(), Severity::Info);
+        assert_eq!(Builtin::Internal.severity(), Severity::Error);
+        assert_eq!(Builtin::DataLoss.severity(), Severity::Critical);
+        assert_eq!(Severity::infer(&Builtin::DataLoss), Severity::Error);
+    }
+
+    #[cfg(feature = "pvlost")]
+    #[test]
+    fn test_pvlost() {
+        use crate::PVLost;
+        assert_eq!(Builtin::Unavailable.severity(), Severity::Error);
+        assert_eq!(Severity::Error.with_pvlost(PVLost::Successful), Severity::Info);
+        assert_eq!(Severity::Error.with_pvlost(PVLost::LocalError), Severity::Warn);
+        assert_eq!(Severity::Debug.with_pvlost(PVLost::LocalError), Severity::Debug);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log() {
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<(log::Level, String)>>);
+
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                self.0.lock().unwrap().push((record.level(), record.args().to_string()));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+        log::set_logger(&CAPTURE).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let err = crate::APIError { meta: &Builtin::NotFound, error: anyhow::anyhow!("user 42"), caller: None, errorspace: None };
+        super::log_error(&anyhow::Error::new(err));
+        super::log_error(&anyhow::anyhow!("plain"));
+        let records = CAPTURE.0.lock().unwrap();
+        assert_eq!(records[0].0, log::Level::Info);
+        assert!(records[0].1.starts_with("[info] "));
+        assert_eq!(records[1], (log::Level::Error, "plain".to_string()));
+    }
+}
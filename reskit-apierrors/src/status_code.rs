@@ -0,0 +1,182 @@
+//! Status codes of api error metas, including non-standard ones.
+//!
+//! `http_types::StatusCode` only has the standard codes, `Status` wraps them and also permits custom
+//! codes registered with a reason phrase, e.g. 499 Client Closed Request. A meta with a custom code
+//! still answers `APIErrorMeta::status_code` with the standard code of its class, 499 as 400, and
+//! reports the custom code with `APIErrorMeta::status`:
+//!
+//! ```ignore
+//! #[apierrormeta(system="", code="24", message="Request cancelled by client.", status_code=499)]
+//! Cancelled,
+//! ```
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{PoisonError, RwLock};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref REASONS: RwLock<BTreeMap<u16, &'static str>> = RwLock::new(
+        [
+            (420, "Enhance Your Calm"),
+            (499, "Client Closed Request"),
+            (520, "Web Server Returned an Unknown Error"),
+        ]
+        .iter()
+        .copied()
+        .collect()
+    );
+}
+
+/// Status is a standard http status code or a registered custom one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Status(u16);
+
+impl Status {
+    /// new create a status code without checking it is standard or registered, codes must be in 100..=599
+    pub const fn new(code: u16) -> Status {
+        Status(code)
+    }
+
+    pub fn code(&self) -> u16 {
+        self.0
+    }
+
+    /// register a custom code with its reason phrase, fails for standard or registered codes
+    pub fn register(code: u16, reason: &'static str) -> anyhow::Result<Status> {
+        if !(100..=599).contains(&code) {
+            anyhow::bail!("Invalid status code {}", code);
+        }
+        if http_types::StatusCode::try_from(code).is_ok() {
+            anyhow::bail!("status code {} is standard", code);
+        }
+        let mut reasons = REASONS.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(existing) = reasons.get(&code) {
+            anyhow::bail!("status code {} is registered as {:?}", code, existing);
+        }
+        reasons.insert(code, reason);
+        Ok(Status(code))
+    }
+
+    /// is_standard returns true if http_types knows the code
+    pub fn is_standard(&self) -> bool {
+        http_types::StatusCode::try_from(self.0).is_ok()
+    }
+
+    /// canonical_reason returns the reason phrase of standard and registered codes, "Unknown" otherwise
+    pub fn canonical_reason(&self) -> &'static str {
+        match http_types::StatusCode::try_from(self.0) {
+            Ok(status_code) => status_code.canonical_reason(),
+            Err(_) => REASONS.read().unwrap_or_else(PoisonError::into_inner).get(&self.0).copied().unwrap_or("Unknown"),
+        }
+    }
+
+    /// to_http returns the standard code, or the first code of the class for custom ones, e.g. 400 for 499
+    pub fn to_http(&self) -> http_types::StatusCode {
+        if let Ok(status_code) = http_types::StatusCode::try_from(self.0) {
+            return status_code;
+        }
+        match self.0 {
+            100..=199 => http_types::StatusCode::Continue,
+            200..=299 => http_types::StatusCode::Ok,
+            300..=399 => http_types::StatusCode::MultipleChoice,
+            400..=499 => http_types::StatusCode::BadRequest,
+            _ => http_types::StatusCode::InternalServerError,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+}
+
+impl From<http_types::StatusCode> for Status {
+    fn from(status_code: http_types::StatusCode) -> Status {
+        Status(status_code as u16)
+    }
+}
+
+impl From<Status> for u16 {
+    fn from(status_code: Status) -> u16 {
+        status_code.0
+    }
+}
+
+impl TryFrom<u16> for Status {
+    type Error = anyhow::Error;
+
+    /// try_from accepts standard and registered codes
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        let status_code = Status(code);
+        if status_code.is_standard() || REASONS.read().unwrap_or_else(PoisonError::into_inner).contains_key(&code) {
+            Ok(status_code)
+        } else {
+            anyhow::bail!("Invalid status code {}", code)
+        }
+    }
+}
+
+impl PartialEq<http_types::StatusCode> for Status {
+    fn eq(&self, other: &http_types::StatusCode) -> bool {
+        self.0 == *other as u16
+    }
+}
+
+impl PartialEq<u16> for Status {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use crate::{Builtin, APIErrorMeta};
+    use super::Status;
+
+    #[test]
+    fn test_status_code() {
+        let cancelled = Status::new(499);
+        assert!(!cancelled.is_standard());
+        assert!(cancelled.is_client_error());
+        assert_eq!(cancelled.canonical_reason(), "Client Closed Request");
+        assert_eq!(cancelled.to_http(), http_types::StatusCode::BadRequest);
+        assert_eq!(Status::new(520).to_http(), http_types::StatusCode::InternalServerError);
+        assert_eq!(Status::from(http_types::StatusCode::NotFound), 404);
+        assert_eq!(Status::new(404).canonical_reason(), "Not Found");
+        assert!(Status::try_from(498).is_err());
+        assert!(Status::try_from(499).is_ok());
+    }
+
+    #[test]
+    fn test_register() {
+        let status_code = Status::register(497, "Invalid Token").unwrap();
+        assert_eq!(status_code.canonical_reason(), "Invalid Token");
+        assert_eq!(Status::try_from(497).unwrap(), status_code);
+        assert!(Status::register(497, "Again").is_err());
+        assert!(Status::register(404, "Not Here").is_err());
+        assert!(Status::register(600, "Out Of Range").is_err());
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert_eq!(Builtin::Cancelled.status(), 499);
+        assert_eq!(Builtin::Cancelled.status_code(), http_types::StatusCode::BadRequest);
+        assert_eq!(Builtin::NotFound.status(), http_types::StatusCode::NotFound);
+    }
+}
@@ -0,0 +1,239 @@
+//! Tracing integration, enabled by the `tracing` feature.
+//!
+//! Every APIError created by `adapt!`/`force!` emits an event with target `reskit_apierrors` at the
+//! level of its severity, with the fields system, code, status_code, pvlost, severity, caller and chain.
+//! The error is also recorded on the current span if the span declares the fields:
+//!
+//! ```ignore
+//! let span = tracing::info_span!("request", apierror.system = Empty, apierror.code = Empty, apierror.status_code = Empty);
+//! ```
+//!
+//! `ErrorSpanLayer` marks the span of the event and its ancestors with the most severe error, so traces
+//! show which error code failed a request.
+use std::fmt;
+
+use tracing::field::{Field, Visit};
+use tracing::span::Id;
+use tracing::{Event, Level, Span, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::{APIError, APIErrorMeta, Severity};
+
+/// TARGET is the target of the events emitted for api errors
+pub const TARGET: &str = "reskit_apierrors";
+
+/// record emit the event of a created api error and record it on the current span
+pub(crate) fn record(err: &APIError) {
+    let status_code = err.status().code();
+    let severity = err.severity();
+    let caller = err.caller.unwrap_or("");
+    let chain = format!("{:#}", err.error);
+    #[cfg(feature = "pvlost")]
+    let pvlost = Some(u8::from(err.pvlost()));
+    #[cfg(not(feature = "pvlost"))]
+    let pvlost: Option<u8> = None;
+
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!(
+                target: TARGET,
+                $level,
+                system = err.system(),
+                code = err.code(),
+                status_code,
+                pvlost,
+                severity = severity.as_str(),
+                caller,
+                chain = chain.as_str(),
+                "{}",
+                err.public_message(),
+            )
+        };
+    }
+    match severity {
+        Severity::Debug => emit!(Level::DEBUG),
+        Severity::Info => emit!(Level::INFO),
+        Severity::Warn => emit!(Level::WARN),
+        Severity::Error | Severity::Critical => emit!(Level::ERROR),
+    }
+
+    let span = Span::current();
+    span.record("apierror.system", err.system());
+    span.record("apierror.code", err.code());
+    span.record("apierror.status_code", status_code);
+}
+
+/// SpanError is the api error a span is marked with, stored in the span extensions by `ErrorSpanLayer`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanError {
+    pub system: String,
+    pub code: String,
+    pub status_code: u16,
+    pub severity: Severity,
+}
+
+impl SpanError {
+    /// is_errored returns true if the span ended with an error of error severity or worse
+    pub fn is_errored(&self) -> bool {
+        self.severity >= Severity::Error
+    }
+
+    /// is_more_severe compares the severity, then the status code, an equally severe error is not more severe
+    pub fn is_more_severe(&self, other: &SpanError) -> bool {
+        (self.severity, self.status_code) > (other.severity, other.status_code)
+    }
+}
+
+impl Default for SpanError {
+    fn default() -> SpanError {
+        SpanError { system: String::new(), code: String::new(), status_code: 0, severity: Severity::Debug }
+    }
+}
+
+impl Visit for SpanError {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "system" => self.system = value.to_string(),
+            "code" => self.code = value.to_string(),
+            "severity" => self.severity = value.parse().unwrap_or(Severity::Error),
+            _ => {},
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "status_code" {
+            self.status_code = value as u16;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+type OnClose = Box<dyn Fn(&str, &SpanError) + Send + Sync>;
+
+/// ErrorSpanLayer marks spans with the most severe api error recorded in them, the first one among equally severe errors
+#[derive(Default)]
+pub struct ErrorSpanLayer {
+    on_close: Option<OnClose>,
+}
+
+impl ErrorSpanLayer {
+    pub fn new() -> ErrorSpanLayer {
+        ErrorSpanLayer::default()
+    }
+
+    /// on_close call f with the name and the error of every marked span when it closes, e.g. to set the trace status
+    pub fn on_close(mut self, f: impl Fn(&str, &SpanError) + Send + Sync + 'static) -> ErrorSpanLayer {
+        self.on_close = Some(Box::new(f));
+        self
+    }
+}
+
+impl<S> Layer<S> for ErrorSpanLayer where S: Subscriber + for<'a> LookupSpan<'a> {
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != TARGET {
+            return;
+        }
+        let span = match ctx.event_span(event) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut err = SpanError::default();
+        event.record(&mut err);
+        for span in span.scope() {
+            let mut extensions = span.extensions_mut();
+            match extensions.get_mut::<SpanError>() {
+                Some(marked) if !err.is_more_severe(marked) => {},
+                Some(marked) => *marked = err.clone(),
+                None => extensions.insert(err.clone()),
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let (on_close, span) = match (&self.on_close, ctx.span(&id)) {
+            (Some(on_close), Some(span)) => (on_close, span),
+            _ => return,
+        };
+        let extensions = span.extensions();
+        if let Some(err) = extensions.get::<SpanError>() {
+            on_close(span.name(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use reskit_utils::init_once;
+    use tracing_subscriber::layer::SubscriberExt;
+    use crate::{Builtin, Severity, adapt, force};
+    use super::{ErrorSpanLayer, SpanError};
+
+    #[test]
+    fn test_layer() {
+        init_once();
+        let closed: Arc<Mutex<Vec<(String, SpanError)>>> = Arc::default();
+        let sink = closed.clone();
+        let layer = ErrorSpanLayer::new().on_close(move |name, err| sink.lock().unwrap().push((name.to_string(), err.clone())));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request");
+            let _request = request.enter();
+            {
+                let query = tracing::info_span!("query");
+                let _query = query.enter();
+                let _ = force!(anyhow::anyhow!("connection refused"), &Builtin::Unavailable);
+            }
+            let _ = adapt!(anyhow::anyhow!("user 42"), &Builtin::NotFound);
+        });
+        let closed = closed.lock().unwrap();
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].0, "query");
+        assert_eq!(closed[0].1.code, "26");
+        assert_eq!(closed[0].1.status_code, 503);
+        assert!(closed[0].1.is_errored());
+        assert_eq!(closed[1].0, "request");
+        assert_eq!(closed[1].1.code, "26");
+        assert!(closed[1].1.is_errored());
+    }
+
+    #[test]
+    fn test_most_severe() {
+        init_once();
+        let closed: Arc<Mutex<Vec<(String, SpanError)>>> = Arc::default();
+        let sink = closed.clone();
+        let layer = ErrorSpanLayer::new().on_close(move |name, err| sink.lock().unwrap().push((name.to_string(), err.clone())));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request");
+            let _request = request.enter();
+            let _ = force!(anyhow::anyhow!("user 42"), &Builtin::NotFound);
+            let _ = force!(anyhow::anyhow!("user 43"), &Builtin::NotFound);
+            let _ = force!(anyhow::anyhow!("bad id"), &Builtin::InvalidArgument);
+        });
+        let closed = closed.lock().unwrap();
+        assert_eq!(closed[0].1.code, "7");
+        assert_eq!(closed[0].1.status_code, 404);
+        assert_eq!(closed[0].1.severity, Severity::Info);
+    }
+
+    #[test]
+    fn test_rewrap() {
+        init_once();
+        let closed: Arc<Mutex<Vec<(String, SpanError)>>> = Arc::default();
+        let sink = closed.clone();
+        let layer = ErrorSpanLayer::new().on_close(move |name, err| sink.lock().unwrap().push((name.to_string(), err.clone())));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request");
+            let _request = request.enter();
+            let not_found = force!(anyhow::anyhow!("user 42"), &Builtin::NotFound);
+            let _ = force!(not_found, &Builtin::Internal);
+        });
+        let closed = closed.lock().unwrap();
+        assert_eq!(closed[0].1.code, "2");
+        assert_eq!(closed[0].1.status_code, 500);
+    }
+}
@@ -0,0 +1,353 @@
+//! Catalog consistency checks.
+//!
+//! A `Validator` runs a list of `Rule`s over api error metas and collects the violations into
+//! a `Report`, usable from tests and startup checks:
+//!
+//! ```ignore
+//! let report = ERRORSPACES.read().unwrap()[""].validate();
+//! assert!(report.is_ok(), "{}", report);
+//! ```
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::{APIErrorMeta, APIErrorMetas};
+
+/// Level tells whether a violation fails the validation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Level::Warning => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Violation is one inconsistency found by a rule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub level: Level,
+    pub system: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(rule: &'static str, level: Level, meta: &dyn APIErrorMeta, message: impl Into<String>) -> Violation {
+        Violation {
+            rule,
+            level,
+            system: meta.system().to_string(),
+            code: meta.code().to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}[{}] {}:{}: {}", self.level, self.rule, self.system, self.code, self.message)
+    }
+}
+
+/// Report collects the violations of a validation
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    pub fn push(&mut self, violation: Violation) {
+        self.violations.push(violation);
+    }
+
+    /// is_ok returns true if there is no error level violation
+    pub fn is_ok(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Violation> {
+        self.violations.iter().filter(|v| v.level == Level::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Violation> {
+        self.violations.iter().filter(|v| v.level == Level::Warning)
+    }
+
+    /// into_result fails with the report if there is any error level violation, e.g. to abort startup
+    pub fn into_result(self) -> Result<Report, Report> {
+        if self.is_ok() {
+            Ok(self)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for violation in &self.violations {
+            writeln!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Report {}
+
+/// Rule checks the api error metas of a catalog as a whole
+pub trait Rule: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn check(&self, metas: &[&dyn APIErrorMeta], report: &mut Report);
+}
+
+/// Validator runs its rules over api error metas
+pub struct Validator {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Validator {
+    /// default validator with all builtin rules
+    fn default() -> Validator {
+        Validator::new()
+            .with_rule(UniqueCodes)
+            .with_rule(StatusConsistency)
+            .with_rule(MessageStyle::default())
+            .with_rule(SystemNaming)
+    }
+}
+
+impl Validator {
+    /// new create a validator without rules
+    pub fn new() -> Validator {
+        Validator { rules: Vec::new() }
+    }
+
+    pub fn with_rule(mut self, rule: impl Rule + 'static) -> Validator {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn validate(&self, metas: &[&dyn APIErrorMeta]) -> Report {
+        let mut report = Report::default();
+        for rule in &self.rules {
+            rule.check(metas, &mut report);
+        }
+        report
+    }
+
+    /// validate_metas validate APIErrorMetas before registering, duplicates are still visible here
+    pub fn validate_metas<T: APIErrorMetas>(&self) -> Report {
+        self.validate(&T::api_error_metas())
+    }
+}
+
+/// UniqueCodes rejects duplicated system:code, numeric codes are compared by value so "01" collides with "1"
+/// 
+/// An errorspace keeps one meta per system:code, so over registered metas only numeric collisions
+/// are left to find, run `Validator::validate_metas` on the registration input to catch exact duplicates.
+pub struct UniqueCodes;
+
+impl Rule for UniqueCodes {
+    fn name(&self) -> &'static str {
+        "unique_codes"
+    }
+
+    fn check(&self, metas: &[&dyn APIErrorMeta], report: &mut Report) {
+        let mut seen: HashMap<(&str, String), &dyn APIErrorMeta> = HashMap::new();
+        for &meta in metas {
+            let code = match meta.code().parse::<u64>() {
+                Ok(num) => num.to_string(),
+                Err(_) => meta.code().to_string(),
+            };
+            if let Some(first) = seen.insert((meta.system(), code), meta) {
+                report.push(Violation::new(self.name(), Level::Error, meta, format!("code collides with {}:{}", first.system(), first.code())));
+            }
+        }
+    }
+}
+
+/// StatusConsistency checks the success status code class agrees with code 0 and whether the pvlost is lost
+pub struct StatusConsistency;
+
+impl Rule for StatusConsistency {
+    fn name(&self) -> &'static str {
+        "status_consistency"
+    }
+
+    fn check(&self, metas: &[&dyn APIErrorMeta], report: &mut Report) {
+        for &meta in metas {
+            let status_code = meta.status_code();
+            let success = !status_code.is_client_error() && !status_code.is_server_error();
+            if meta.code() == "0" && !success {
+                report.push(Violation::new(self.name(), Level::Error, meta, format!("code 0 is reserved for success but carries status {}", status_code)));
+            }
+            #[cfg(feature = "pvlost")]
+            {
+                if meta.pvlost().is_lost() == success {
+                    report.push(Violation::new(self.name(), Level::Error, meta, format!("pvlost {} contradicts status {}", meta.pvlost(), status_code)));
+                }
+            }
+        }
+    }
+}
+
+/// MessageStyle requires a non empty message starting with an uppercase letter and ending with the terminator
+pub struct MessageStyle {
+    pub terminator: Option<char>,
+}
+
+impl Default for MessageStyle {
+    fn default() -> MessageStyle {
+        MessageStyle { terminator: Some('.') }
+    }
+}
+
+impl Rule for MessageStyle {
+    fn name(&self) -> &'static str {
+        "message_style"
+    }
+
+    fn check(&self, metas: &[&dyn APIErrorMeta], report: &mut Report) {
+        for &meta in metas {
+            let message = meta.message().trim();
+            if message.is_empty() {
+                report.push(Violation::new(self.name(), Level::Error, meta, "message is empty"));
+                continue;
+            }
+            if message.chars().next().is_some_and(char::is_lowercase) {
+                report.push(Violation::new(self.name(), Level::Warning, meta, "message should start with an uppercase letter"));
+            }
+            if let Some(terminator) = self.terminator {
+                if !message.ends_with(terminator) {
+                    report.push(Violation::new(self.name(), Level::Warning, meta, format!("message should end with {:?}", terminator)));
+                }
+            }
+        }
+    }
+}
+
+/// SystemNaming requires snake_case system names, the global system "" is allowed
+pub struct SystemNaming;
+
+impl Rule for SystemNaming {
+    fn name(&self) -> &'static str {
+        "system_naming"
+    }
+
+    fn check(&self, metas: &[&dyn APIErrorMeta], report: &mut Report) {
+        for &meta in metas {
+            let system = meta.system();
+            let valid = system.is_empty() || (
+                system.starts_with(|c: char| c.is_ascii_lowercase())
+                && system.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            );
+            if !valid {
+                report.push(Violation::new(self.name(), Level::Error, meta, "system should be snake_case"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Builtin, Errorspace, AsAPIErrorMeta, prelude::*};
+    use super::{Validator, Level, UniqueCodes, MessageStyle};
+
+    #[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+    enum Bad {
+        #[apierrormeta(system="bad", code="1", message="First.", status_code=500)]
+        First,
+        #[apierrormeta(system="bad", code="01", message="Second.", status_code=500)]
+        Second,
+        #[apierrormeta(system="bad", code="0", message="Successful.", status_code=503)]
+        Successful,
+        #[apierrormeta(system="bad", code="2", message="", status_code=400)]
+        Empty,
+        #[apierrormeta(system="bad", code="3", message="lower case", status_code=400)]
+        Style,
+        #[apierrormeta(system="Bad-System", code="1", message="Naming.", status_code=400)]
+        Naming,
+        #[apierrormeta(system="bad", code="1", message="Duplicate.", status_code=500)]
+        Duplicate,
+    }
+
+    #[test]
+    fn test_builtin() {
+        let report = Validator::default().validate_metas::<Builtin>();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.warnings().count(), 0, "{}", report);
+        let mut space = Errorspace::new();
+        for meta in Builtin::api_error_metas() {
+            space.register_api_error_meta(meta);
+        }
+        assert!(space.validate().is_ok());
+    }
+
+    #[test]
+    fn test_violations() {
+        let report = Validator::default().validate_metas::<Bad>();
+        assert!(!report.is_ok());
+        let rules: Vec<_> = report.errors().map(|v| (v.rule, v.code.as_str())).collect();
+        assert!(rules.contains(&("unique_codes", "01")));
+        assert_eq!(report.errors().filter(|v| v.rule == "unique_codes").count(), 2);
+        assert!(rules.contains(&("status_consistency", "0")));
+        assert!(rules.contains(&("message_style", "2")));
+        assert!(rules.contains(&("system_naming", "1")));
+        let warnings: Vec<_> = report.warnings().map(|v| (v.rule, v.code.as_str())).collect();
+        assert_eq!(warnings, vec![("message_style", "3"), ("message_style", "3")]);
+        assert!(report.clone().into_result().is_err());
+        assert!(report.to_string().contains("error[unique_codes] bad:01: code collides with bad:1\n"));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let validator = Validator::new()
+            .with_rule(UniqueCodes)
+            .with_rule(MessageStyle { terminator: None });
+        let report = validator.validate_metas::<Bad>();
+        assert_eq!(report.violations.iter().filter(|v| v.level == Level::Warning).count(), 1);
+        assert_eq!(report.errors().count(), 3);
+    }
+
+    #[test]
+    fn test_registered() {
+        let mut space = Errorspace::new();
+        for meta in Bad::api_error_metas() {
+            space.register_api_error_meta(meta);
+        }
+        let report = Validator::new().with_rule(UniqueCodes).validate(&space.iter().collect::<Vec<_>>());
+        let codes: Vec<_> = report.errors().map(|v| v.code.as_str()).collect();
+        assert_eq!(codes.len(), 1);
+        assert!(codes[0] == "1" || codes[0] == "01");
+    }
+
+    #[cfg(feature = "pvlost")]
+    #[test]
+    fn test_pvlost_reason() {
+        use crate::{PVLost, Reason};
+        use super::StatusConsistency;
+
+        #[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+        enum Reasons {
+            #[apierrormeta(system="reasons", code="1", message="Cached.", status_code=200, pvlost=30)]
+            Cached,
+            #[apierrormeta(system="reasons", code="2", message="Failed.", status_code=200, pvlost="remote_error")]
+            Failed,
+        }
+
+        assert_eq!(Reasons::Cached.pvlost(), PVLost::new(30));
+        PVLost::register(Reason { code: 30, name: "cache_hit", description: "Cache Hit", lost: false, local: false }).unwrap();
+        assert_eq!(Reasons::Cached.pvlost(), PVLost::new(30));
+        let report = Validator::new().with_rule(StatusConsistency).validate_metas::<Reasons>();
+        let codes: Vec<_> = report.errors().map(|v| v.code.as_str()).collect();
+        assert_eq!(codes, vec!["2"]);
+    }
+}
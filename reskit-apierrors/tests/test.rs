@@ -1,6 +1,7 @@
-
 #[test]
 fn test() {
     let t = trybuild::TestCases::new();
     t.pass("tests/tests/derive-pass-0.rs");
-}
\ No newline at end of file
+    t.pass("tests/tests/derive-pass-1.rs");
+    t.pass("tests/tests/derive-pass-2.rs");
+}
@@ -0,0 +1,30 @@
+use reskit_apierrors::{prelude::*, AsAPIErrorMeta};
+
+#[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+pub enum DocTest {
+    /// Line doc.
+    ///
+    ///     indented
+    #[apierrormeta(system="", code="1", message="Line.", status_code=500)]
+    Line,
+
+    /**
+        Block doc.
+    */
+    #[apierrormeta(system="", code="2", message="Block.", status_code=500)]
+    Block,
+
+    /// Ignored doc.
+    #[apierrormeta(system="", code="3", message="Override.", status_code=500, description="Override doc.")]
+    Override,
+
+    #[apierrormeta(system="", code="4", message="Undocumented.", status_code=500)]
+    Undocumented,
+}
+
+fn main() {
+    assert_eq!(DocTest::Line.description(), "Line doc.\n\n    indented");
+    assert_eq!(DocTest::Block.description(), "Block doc.");
+    assert_eq!(DocTest::Override.description(), "Override doc.");
+    assert_eq!(DocTest::Undocumented.description(), "");
+}
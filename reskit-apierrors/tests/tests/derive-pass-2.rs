@@ -0,0 +1,48 @@
+use reskit_apierrors::{prelude::*, AsAPIErrorMeta};
+use http_types::StatusCode;
+
+#[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+pub enum Inferred {
+    #[apierrormeta(system="", code="1", message="Invalid.", status_code=400)]
+    Invalid,
+
+    #[apierrormeta(system="", code="2", message="Internal.", status_code=500)]
+    Internal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+#[apierrormetas(pvlost="local_error")]
+pub enum Local {
+    #[apierrormeta(system="", code="1", message="Upstream.", status_code=502)]
+    Upstream,
+
+    #[apierrormeta(system="", code="2", message="Remote.", status_code=502, pvlost=1)]
+    Remote,
+}
+
+fn lenient(status_code: StatusCode) -> PVLost {
+    if status_code == StatusCode::TooManyRequests {
+        PVLost::Successful
+    } else {
+        PVLost::from_status_code(status_code)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, AsAPIErrorMeta)]
+#[apierrormetas(pvlost_policy="lenient")]
+pub enum Policed {
+    #[apierrormeta(system="", code="1", message="Throttled.", status_code=429)]
+    Throttled,
+
+    #[apierrormeta(system="", code="2", message="Invalid.", status_code=400)]
+    Invalid,
+}
+
+fn main() {
+    assert_eq!(Inferred::Invalid.pvlost(), PVLost::LocalError);
+    assert_eq!(Inferred::Internal.pvlost(), PVLost::RemoteError);
+    assert_eq!(Local::Upstream.pvlost(), PVLost::LocalError);
+    assert_eq!(Local::Remote.pvlost(), PVLost::RemoteError);
+    assert_eq!(Policed::Throttled.pvlost(), PVLost::Successful);
+    assert_eq!(Policed::Invalid.pvlost(), PVLost::LocalError);
+}
@@ -1,7 +1,13 @@
 #[macro_use]
 extern crate shadow_rs;
 
-shadow!(build);
+// the generated shadow.rs has blank lines after its doc comments
+#[allow(clippy::empty_line_after_doc_comments)]
+mod shadow {
+    shadow!(build);
+}
+
+pub use shadow::build;
 
 mod macros;
 mod init;
@@ -11,6 +17,7 @@ pub use init::{INIT_FNS, init_once};
 #[cfg(test)]
 mod tests {
     #[test]
+    #[allow(clippy::empty_line_after_doc_comments)]
     fn test_version() {
         shadow!(build);
         assert_eq!(build::PKG_VERSION, "0.1.0"); 
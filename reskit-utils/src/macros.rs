@@ -48,6 +48,7 @@ macro_rules! fn_name {
 #[cfg(test)]
 mod tests {
     #[test]
+    #[allow(clippy::int_plus_one)]
     fn macros_test() {
         assert_eq!(caller!(), "reskit_utils::macros::tests::macros_test");
         assert_eq!(caller!(4), "reskit_utils::macros::tests::macros_test");